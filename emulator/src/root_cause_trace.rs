@@ -2,12 +2,18 @@ use anyhow::{anyhow, Context, Result};
 use capstone::arch::arm::{self, ArmInsn, ArmOperandType};
 use capstone::prelude::*;
 use common::fs::bufwriter;
-use common::{hashbrown::hash_map::Entry, FxHashMap};
+use common::{hashbrown::hash_map::Entry, FxHashMap, FxHashSet};
 use frametracer::AccessType;
 use qemu_rs::{memory::MemoryType, qcontrol, Address, ConditionCode, FlagBits, Register, USize};
 use serde::Serialize;
 use std::collections::HashMap;
-use std::{fmt::Debug, io::Write, ops::Range, path::Path, path::PathBuf};
+use std::{
+    fmt::Debug,
+    io::{self, Write},
+    ops::Range,
+    path::Path,
+    path::PathBuf,
+};
 
 use rand::Rng;
 use serde_json;
@@ -37,31 +43,357 @@ struct EdgeInfo {
     count: u64,
 }
 
+/// Max number of distinct values tracked for an indirect branch's target
+/// register before we give up trusting it as a discrete jump-table/vtable
+/// dispatch and leave the edge as plain `Indirect`.
+const INDIRECT_VALUE_SET_CAP: usize = 8;
+
+/// Upper bound on a `TBB`/`TBH` table's entry count used when no
+/// already-recovered edge above the branch is available to bound the walk
+/// more precisely (the common case: the first time a given table branch
+/// fires, none of its own case targets are known yet). Without this cap the
+/// walk falls back to "scan to the end of the enclosing memory block",
+/// which for a flash region can be several hundred KB of arbitrary
+/// code/data bytes misread as table entries. Real switch-case/vtable
+/// dispatch tables stay well under this many arms.
+const MAX_TABLE_ENTRIES: Address = 256;
+
+/// Which entry width a `TBB`/`TBH` table branch uses for its offset table.
+#[derive(Debug, Clone, Copy)]
+enum TableBranchKind {
+    /// `TBB [pc, rN]`: one unsigned byte per case
+    Byte,
+    /// `TBH [pc, rN, lsl #1]`: one unsigned halfword per case
+    Halfword,
+}
+
+/// A basic block: a maximal straight-line run of instructions reached and
+/// left without any intervening branch. Addresses are folded into the same
+/// block as their single predecessor as long as that predecessor edge is
+/// `Regular`/`Direct` and the address itself has no other predecessor.
+#[derive(Debug, Default)]
+pub struct BasicBlock {
+    /// addresses of the instructions that make up this block, in execution
+    /// order; `instructions[0]` is the block's start PC
+    pub instructions: Vec<Address>,
+    /// blocks this one can transfer control to, and how
+    pub successors: Vec<(Address, EdgeType)>,
+    /// blocks that can transfer control into this one
+    pub predecessors: FxHashSet<Address>,
+    /// subset of `successors` (by target block) reached via an `Indirect`
+    /// edge that was actually resolved from a tracked register value,
+    /// rather than left as the unresolved speculative range. `EdgeType` has
+    /// no separate variant for this distinction (see `Cfg::build`), so it's
+    /// tracked here instead.
+    pub resolved_indirect_successors: FxHashSet<Address>,
+}
+
+/// A basic-block control-flow graph folded out of the raw edge map: a block
+/// ends at any instruction whose `EdgeType` is `Conditional`, `Indirect`,
+/// `Return` or `Syscall`, or at any address that is the target of more than
+/// one edge; otherwise a `Regular`/`Direct` successor with no other
+/// predecessor gets merged straight into the block that reaches it.
+pub struct Cfg {
+    blocks: FxHashMap<Address, BasicBlock>,
+    block_of: FxHashMap<Address, Address>,
+}
+
+impl Cfg {
+    /// `resolved_indirect` is the subset of `edges` that `on_instruction`
+    /// recovered from a tracked value-range rather than leaving as the
+    /// unresolved speculative `Indirect` case (see `resolve_indirect_targets`
+    /// and `BasicBlock::resolved_indirect_successors`).
+    fn build(
+        edges: &FxHashMap<Edge, EdgeInfo>,
+        first_address: Address,
+        resolved_indirect: &FxHashSet<Edge>,
+    ) -> Cfg {
+        let mut predecessors: FxHashMap<Address, FxHashSet<Address>> = FxHashMap::default();
+        for edge in edges.keys() {
+            predecessors.entry(edge.to).or_default().insert(edge.from);
+        }
+
+        let mut block_starts: FxHashSet<Address> = FxHashSet::default();
+        block_starts.insert(first_address);
+        for (edge, info) in edges {
+            if matches!(
+                info.edge_type,
+                EdgeType::Conditional | EdgeType::Indirect | EdgeType::Return | EdgeType::Syscall
+            ) {
+                block_starts.insert(edge.to);
+            }
+        }
+        for (&to, preds) in &predecessors {
+            if preds.len() > 1 {
+                block_starts.insert(to);
+            }
+        }
+
+        // coalesce each block start into the straight-line run of
+        // Regular/Direct edges that follows it, stopping at the next block
+        // start: this is exactly the "sole successor with no other
+        // predecessor gets merged in" rule, applied incrementally
+        let mut block_of: FxHashMap<Address, Address> = FxHashMap::default();
+        let mut block_instructions: FxHashMap<Address, Vec<Address>> = FxHashMap::default();
+        for &start in &block_starts {
+            let mut addrs = vec![start];
+            let mut current = start;
+
+            while let Some((edge, _)) = edges.iter().find(|(edge, info)| {
+                edge.from == current
+                    && matches!(info.edge_type, EdgeType::Regular | EdgeType::Direct)
+                    && !block_starts.contains(&edge.to)
+            }) {
+                addrs.push(edge.to);
+                current = edge.to;
+            }
+
+            for &addr in &addrs {
+                block_of.insert(addr, start);
+            }
+            block_instructions.insert(start, addrs);
+        }
+
+        let mut blocks: FxHashMap<Address, BasicBlock> = block_instructions
+            .into_iter()
+            .map(|(start, instructions)| {
+                (
+                    start,
+                    BasicBlock {
+                        instructions,
+                        ..Default::default()
+                    },
+                )
+            })
+            .collect();
+
+        for (edge, info) in edges {
+            let from_block = block_of.get(&edge.from).copied().unwrap_or(edge.from);
+            let to_block = block_of.get(&edge.to).copied().unwrap_or(edge.to);
+
+            // intra-block straight-line edge, already folded into one node
+            if from_block == to_block {
+                continue;
+            }
+
+            if let Some(block) = blocks.get_mut(&from_block) {
+                block.successors.push((to_block, info.edge_type));
+
+                if resolved_indirect.contains(edge) {
+                    block.resolved_indirect_successors.insert(to_block);
+                }
+            }
+            if let Some(block) = blocks.get_mut(&to_block) {
+                block.predecessors.insert(from_block);
+            }
+        }
+
+        Cfg { blocks, block_of }
+    }
+
+    /// The start PC of the block containing `pc`, if `pc` has been seen.
+    pub fn block_of(&self, pc: Address) -> Option<Address> {
+        self.block_of.get(&pc).copied()
+    }
+
+    pub fn block(&self, block_id: Address) -> Option<&BasicBlock> {
+        self.blocks.get(&block_id)
+    }
+
+    pub fn blocks(&self) -> impl Iterator<Item = (&Address, &BasicBlock)> {
+        self.blocks.iter()
+    }
+
+    /// Export the CFG as a Graphviz DOT graph for debugging: one node per
+    /// basic block (address range, per-instruction mnemonics/counts, and
+    /// any register whose min/max range actually varied across the block)
+    /// and one edge per block successor, colored by `EdgeType`.
+    pub fn to_dot(&self, instructions: &FxHashMap<Address, InstructionData>) -> String {
+        let mut dot = String::from("digraph cfg {\n  node [shape=box, fontname=monospace];\n");
+
+        for (&start, block) in &self.blocks {
+            let end = block.instructions.last().copied().unwrap_or(start);
+
+            let body = block
+                .instructions
+                .iter()
+                .filter_map(|addr| instructions.get(addr).map(|inst| (addr, inst)))
+                .map(|(addr, inst)| format!("0x{:x}: {} (x{})", addr, inst.mnemonic, inst.count))
+                .collect::<Vec<_>>()
+                .join("\\l");
+
+            let reg_ranges = block
+                .instructions
+                .iter()
+                .filter_map(|addr| instructions.get(addr))
+                .flat_map(|inst| {
+                    (0..Register::AMOUNT).filter_map(move |i| {
+                        (inst.min_vals[i].is_set
+                            && inst.max_vals[i].is_set
+                            && inst.min_vals[i].value != inst.max_vals[i].value)
+                            .then(|| {
+                                format!(
+                                    "r{}: [0x{:x}, 0x{:x}]",
+                                    i, inst.min_vals[i].value, inst.max_vals[i].value
+                                )
+                            })
+                    })
+                })
+                .collect::<FxHashSet<_>>()
+                .into_iter()
+                .collect::<Vec<_>>()
+                .join("\\l");
+
+            let label = if reg_ranges.is_empty() {
+                format!("0x{:x}-0x{:x}\\l{body}\\l", start, end)
+            } else {
+                format!("0x{:x}-0x{:x}\\l{body}\\l{reg_ranges}\\l", start, end)
+            };
+
+            dot.push_str(&format!("  \"0x{:x}\" [label=\"{}\"];\n", start, label));
+
+            for &(to_block, edge_type) in &block.successors {
+                let color = match edge_type {
+                    EdgeType::Regular | EdgeType::Direct => "black",
+                    EdgeType::Conditional => "blue",
+                    EdgeType::Return => "red",
+                    // resolved from a tracked value-range, not just the
+                    // unresolved speculative case -- trace_analysis::trace::
+                    // EdgeType has no variant for the distinction, so it's
+                    // colored differently here instead
+                    EdgeType::Indirect if block.resolved_indirect_successors.contains(&to_block) => {
+                        "darkgreen"
+                    }
+                    EdgeType::Indirect => "orange",
+                    EdgeType::Syscall => "purple",
+                    EdgeType::Unknown => "gray",
+                    _ => "black",
+                };
+
+                dot.push_str(&format!(
+                    "  \"0x{:x}\" -> \"0x{:x}\" [color=\"{}\"];\n",
+                    start, to_block, color
+                ));
+            }
+        }
+
+        dot.push_str("}\n");
+        dot
+    }
+}
+
 #[derive(Copy, Clone, Serialize)]
 struct Value {
     is_set: bool,
     value: u32,
 }
 
-struct ItState {
-    condition: ConditionCode,
-    state: Vec<bool>,
-}
+/// ARMv7-M ITSTATE: `firstcond = ITSTATE[7:4]`, `mask = ITSTATE[3:0]`.
+///
+/// The condition to evaluate for the *current* in-block instruction is
+/// always the full nibble `ITSTATE[7:4]` (standard ARM condition code, so
+/// AL = 0b1110 needs no special-casing and every then/else inversion is
+/// already baked into bit 0 of the nibble by construction/advance).
+#[derive(Clone, Copy)]
+struct ItState(u8);
 
 impl ItState {
-    pub fn new(condition: ConditionCode, state: Vec<bool>) -> ItState {
-        ItState { condition, state }
+    /// Build ITSTATE from a condition code nibble and a `t`/`e` suffix
+    /// (e.g. `firstcond` for `ITE EQ` is EQ's code, suffix is `[false]`
+    /// for the single `e`), per the standard IT encoding table.
+    fn from_condition_and_suffix(firstcond: u8, suffix: &[bool]) -> ItState {
+        let cond0 = firstcond & 1;
+        let mut mask = 0u8;
+
+        for (i, &is_then) in suffix.iter().enumerate() {
+            let bit = if is_then { cond0 } else { cond0 ^ 1 };
+            mask |= bit << (3 - i);
+        }
+        // terminating '1' right after the suffix bits
+        mask |= 1 << (3 - suffix.len());
+
+        ItState((firstcond << 4) | mask)
+    }
+
+    /// `ITSTATE[7:4]`, the condition code to evaluate for the current
+    /// in-block instruction.
+    fn condition(&self) -> u8 {
+        self.0 >> 4
+    }
+
+    /// ITAdvance: advances to the next in-block instruction, or returns
+    /// `None` once the IT block is finished.
+    fn advance(self) -> Option<ItState> {
+        if self.0 & 0b111 == 0 {
+            None
+        } else {
+            let high3 = self.0 & 0b1110_0000;
+            let low5 = ((self.0 & 0b0001_1111) << 1) & 0b0001_1111;
+            Some(ItState(high3 | low5))
+        }
+    }
+}
+
+/// Evaluate a 4-bit ARM condition code against the NZCV flags in `xpsr`.
+/// AL (0b1110) and the reserved NV (0b1111) both always pass.
+fn condition_passes(condition: u8, xpsr: u32) -> bool {
+    let bit_set = |pos: u8| -> bool { (xpsr & (1 << pos)) != 0 };
+    let n = bit_set(FlagBits::N.to_bit_index() as u8);
+    let z = bit_set(FlagBits::Z.to_bit_index() as u8);
+    let c = bit_set(FlagBits::C.to_bit_index() as u8);
+    let v = bit_set(FlagBits::V.to_bit_index() as u8);
+
+    match condition & 0xf {
+        0b0000 => z,               // EQ
+        0b0001 => !z,              // NE
+        0b0010 => c,               // CS/HS
+        0b0011 => !c,              // CC/LO
+        0b0100 => n,               // MI
+        0b0101 => !n,              // PL
+        0b0110 => v,               // VS
+        0b0111 => !v,              // VC
+        0b1000 => c && !z,         // HI
+        0b1001 => !c || z,         // LS
+        0b1010 => n == v,          // GE
+        0b1011 => n != v,          // LT
+        0b1100 => !z && n == v,    // GT
+        0b1101 => z || n != v,     // LE
+        // AL / NV: always execute, no special-casing
+        _ => true,
     }
 }
 
-#[derive(Default, Debug, Clone, Copy)]
+/// Numeric ARM condition code for a `ConditionCode` as parsed from a
+/// disassembled mnemonic (e.g. the `eq` in `ite eq`).
+fn condition_code_value(condition: ConditionCode) -> u8 {
+    match condition {
+        ConditionCode::EQ => 0b0000,
+        ConditionCode::NE => 0b0001,
+        ConditionCode::CS => 0b0010,
+        ConditionCode::CC => 0b0011,
+        ConditionCode::MI => 0b0100,
+        ConditionCode::PL => 0b0101,
+        ConditionCode::VS => 0b0110,
+        ConditionCode::VC => 0b0111,
+        ConditionCode::HI => 0b1000,
+        ConditionCode::LS => 0b1001,
+        ConditionCode::GE => 0b1010,
+        ConditionCode::LT => 0b1011,
+        ConditionCode::GT => 0b1100,
+        ConditionCode::LE => 0b1101,
+        // AL (and any other/reserved code) always passes, no special-casing
+        _ => 0b1110,
+    }
+}
+
+#[derive(Default, Debug, Clone, Copy, Serialize)]
 struct MemoryField {
     address: Address,
     size: u8,
     value: USize,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Serialize)]
 struct MemoryData {
     last_addr: MemoryField,
     min_addr: MemoryField,
@@ -71,6 +403,62 @@ struct MemoryData {
     max_value: MemoryField,
 }
 
+impl MemoryData {
+    fn is_set(&self) -> bool {
+        self.last_addr.size != 0
+    }
+
+    /// fold one more word of a multi-register/doubleword transfer (LDM/STM,
+    /// LDRD/STRD, ...) into the per-instruction min/max/last fields, rather
+    /// than treating each access as unrelated to the others at this PC.
+    fn record(&mut self, access: MemoryField) {
+        if self.last_addr.size != 0 && self.last_addr.size != access.size {
+            log::info!(
+                "Memory operand has different access sizes at 0x{:x}",
+                access.address
+            );
+        }
+
+        if self.max_addr.address <= access.address {
+            self.max_addr = access;
+        }
+        if self.min_addr.address >= access.address {
+            self.min_addr = access;
+        }
+        self.last_addr = access;
+
+        if self.max_value.value <= access.value {
+            self.max_value = access;
+        }
+        if self.min_value.value >= access.value {
+            self.min_value = access;
+        }
+        self.last_value = access;
+    }
+
+    /// Fold `other` into a copy of `self`, as if every access recorded in
+    /// `other` had also been recorded here. Used to combine the separate
+    /// read/write trackers back into the single flat record the
+    /// `trace_analysis` serialization format has room for.
+    fn merge(&self, other: &MemoryData) -> MemoryData {
+        let mut merged = MemoryData::default();
+
+        for data in [self, other] {
+            if data.is_set() {
+                merged.record(data.min_addr);
+                merged.record(data.max_addr);
+                merged.record(data.min_value);
+                merged.record(data.max_value);
+                // recorded last so the merged last_addr/last_value are
+                // this tracker's last access (`record` sets both to it)
+                merged.record(data.last_addr);
+            }
+        }
+
+        merged
+    }
+}
+
 type Registers = [Value; Register::AMOUNT];
 
 struct InstructionData {
@@ -86,8 +474,23 @@ struct InstructionData {
     last_vals: Registers,
     // last successor of recorded for this inst
     last_successor: Address,
-    // todo: leverage memory models to derive the memory access data ?
-    mem_data: MemoryData,
+    /// write accesses (separate from reads, mirroring the read/write
+    /// distinction moa draws between memory access types)
+    mem_write: MemoryData,
+    /// read accesses
+    mem_read: MemoryData,
+    /// set once a write targeted a read-only memory block; a read of a
+    /// read-only region is fine, a write is suspicious
+    write_to_readonly: bool,
+    /// smallest value observed in this instruction's indirect-branch target
+    /// register (only tracked for `EdgeType::Indirect` instructions)
+    indirect_min: Value,
+    /// largest value observed in the indirect-branch target register
+    indirect_max: Value,
+    /// bounded set of distinct values observed in the indirect-branch
+    /// target register, used to materialize concrete jump targets once the
+    /// range is tight (few discrete values) rather than a wide interval
+    indirect_observed: FxHashSet<u32>,
 }
 
 impl InstructionData {
@@ -108,7 +511,18 @@ impl InstructionData {
                 value: 0,
             }; Register::AMOUNT],
             last_successor: 0,
-            mem_data: MemoryData::default(),
+            mem_write: MemoryData::default(),
+            mem_read: MemoryData::default(),
+            write_to_readonly: false,
+            indirect_min: Value {
+                is_set: false,
+                value: u32::MAX,
+            },
+            indirect_max: Value {
+                is_set: false,
+                value: 0,
+            },
+            indirect_observed: FxHashSet::default(),
         }
     }
 
@@ -130,18 +544,19 @@ impl InstructionData {
         insert_if_set(&mut max_vals, &self.max_vals);
         insert_if_set(&mut last_vals, &self.last_vals);
 
-        let memory = if self.mem_data.last_addr.size != 0 {
-            Some(SerializedMemory {
-                min_address: self.mem_data.min_addr.address as u64,
-                max_address: self.mem_data.max_addr.address as u64,
-                last_address: self.mem_data.last_addr.address as u64,
-                min_value: self.mem_data.min_value.value as u64,
-                max_value: self.mem_data.max_value.value as u64,
-                last_value: self.mem_data.last_value.value as u64,
-            })
-        } else {
-            None
-        };
+        // trace_analysis::trace::Memory only has room for a single flat
+        // min/max/last record, so reads and writes (tracked separately on
+        // InstructionData so on_memory_access can tell them apart) are
+        // merged back together here before serializing.
+        let mem_data = self.mem_write.merge(&self.mem_read);
+        let memory = mem_data.is_set().then(|| SerializedMemory {
+            min_address: mem_data.min_addr.address as u64,
+            max_address: mem_data.max_addr.address as u64,
+            last_address: mem_data.last_addr.address as u64,
+            min_value: mem_data.min_value.value as u64,
+            max_value: mem_data.max_value.value as u64,
+            last_value: mem_data.last_value.value as u64,
+        });
 
         SerializedInstruction {
             address: pc as usize,
@@ -154,11 +569,149 @@ impl InstructionData {
             memory: memory,
         }
     }
+
+    /// The read/write split and `write_to_readonly` flag `to_serialized_instruction`
+    /// has to fold away, kept intact for [`MemoryDetail`]'s side-channel
+    /// artifact (see its doc comment).
+    fn to_memory_detail(&self, pc: Address) -> Option<MemoryDetail> {
+        (self.mem_write.is_set() || self.mem_read.is_set() || self.write_to_readonly).then(|| {
+            MemoryDetail {
+                address: pc as usize,
+                write: self.mem_write.is_set().then(|| self.mem_write.to_serialized()),
+                read: self.mem_read.is_set().then(|| self.mem_read.to_serialized()),
+                write_to_readonly: self.write_to_readonly,
+            }
+        })
+    }
+}
+
+/// Per-instruction memory access detail with read and write accesses still
+/// separate, for the tools that need that distinction. `trace_analysis::
+/// trace::Memory` (and the `SerializedTrace` artifact built from it) only
+/// has room for one flat min/max/last record -- that's a serialization
+/// format owned by the absent `trace_analysis` crate, not something this
+/// series can widen -- so `to_serialized_instruction` merges read and write
+/// back together for that artifact, and this struct carries the unmerged
+/// version as a separate side-channel file written by `post_run` alongside
+/// it, for any in-tree consumer that wants reads and writes apart.
+#[derive(Serialize)]
+struct MemoryDetail {
+    address: usize,
+    write: Option<SerializedMemoryData>,
+    read: Option<SerializedMemoryData>,
+    /// see `InstructionData::write_to_readonly`; also absent from
+    /// `trace_analysis::trace::Memory`.
+    write_to_readonly: bool,
+}
+
+#[derive(Serialize)]
+struct SerializedMemoryData {
+    min_address: u64,
+    max_address: u64,
+    last_address: u64,
+    min_value: u64,
+    max_value: u64,
+    last_value: u64,
+}
+
+impl MemoryData {
+    fn to_serialized(&self) -> SerializedMemoryData {
+        SerializedMemoryData {
+            min_address: self.min_addr.address as u64,
+            max_address: self.max_addr.address as u64,
+            last_address: self.last_addr.address as u64,
+            min_value: self.min_value.value as u64,
+            max_value: self.max_value.value as u64,
+            last_value: self.last_value.value as u64,
+        }
+    }
+}
+
+/// a register watchpoint breaks when `register` leaves `[min, max]`
+struct RegisterWatchpoint {
+    register: Register,
+    min: u32,
+    max: u32,
+}
+
+/// single-step command-REPL debugger, hooked into `on_instruction` /
+/// `on_memory_access` before the trace-recording logic runs. Zero cost when
+/// `RootCauseTrace::debugger` is `None`.
+pub struct Debugger {
+    breakpoints: FxHashSet<Address>,
+    register_watchpoints: Vec<RegisterWatchpoint>,
+    memory_watchpoints: FxHashSet<Address>,
+    /// record instructions/memory accesses but never stop for a prompt
+    trace_only: bool,
+    /// instructions left to execute before stopping again, set by `step`
+    steps_remaining: u64,
+    last_command: Option<String>,
+}
+
+impl Debugger {
+    pub fn new(trace_only: bool) -> Debugger {
+        Debugger {
+            breakpoints: FxHashSet::default(),
+            register_watchpoints: vec![],
+            memory_watchpoints: FxHashSet::default(),
+            trace_only,
+            steps_remaining: 0,
+            last_command: None,
+        }
+    }
+
+    fn register_hit(&self, registers: &[u32]) -> Option<Register> {
+        self.register_watchpoints
+            .iter()
+            .find(|wp| {
+                let value = registers[wp.register as usize];
+                value < wp.min || value > wp.max
+            })
+            .map(|wp| wp.register)
+    }
+
+    fn should_stop(&mut self, pc: Address, registers: &[u32]) -> bool {
+        if self.trace_only {
+            return false;
+        }
+
+        if self.steps_remaining > 0 {
+            self.steps_remaining -= 1;
+            return self.steps_remaining == 0;
+        }
+
+        self.breakpoints.contains(&pc) || self.register_hit(registers).is_some()
+    }
+}
+
+/// Everything `on_instruction` derives from disassembling the instruction at
+/// a given address. Cached per-address since the same PC is usually hit many
+/// times over the course of a trace and Capstone + string formatting is not
+/// free.
+#[derive(Debug, Clone)]
+struct DecodedInsn {
+    mnemonic: Option<String>,
+    edge_type: EdgeType,
+    regs_written: Vec<Register>,
+    is_it: bool,
+    table_branch: Option<TableBranchKind>,
+    /// register operand holding the destination of a still-unresolved
+    /// `EdgeType::Indirect` branch, if any
+    indirect_operand: Option<Register>,
+    /// encoded length in bytes (2 or 4 for Thumb-2), used to compute the
+    /// implicit fall-through address for a predicated control-flow
+    /// instruction inside an IT block; 0 if decoding failed
+    size: u8,
 }
 
 pub struct RootCauseTrace {
     instructions: FxHashMap<Address, InstructionData>,
     edges: FxHashMap<Edge, EdgeInfo>,
+    /// subset of `edges` materialized by `resolve_indirect_targets` from a
+    /// tracked value-range, as opposed to ones `update_edges` added from an
+    /// actually-taken branch; consulted by `cfg()` to color resolved vs.
+    /// speculative indirect edges differently in `to_dot()`.
+    resolved_indirect_edges: FxHashSet<Edge>,
     reg_state: [u32; Register::AMOUNT],
     prev_edge_type: EdgeType,
     prev_ins_addr: Address,
@@ -171,10 +724,36 @@ pub struct RootCauseTrace {
     trace_cnt: u64,
     cs: Capstone,
     detailed_trace_info: Vec<Vec<u32>>,
+    debugger: Option<Debugger>,
+    /// Companion to `debugger`: the CLI output option that asks `post_run`
+    /// to also emit a Graphviz `.dot` CFG dump alongside the bincode
+    /// summary/full artifacts.
+    dot_output: bool,
+    decode_cache: FxHashMap<Address, DecodedInsn>,
+    /// `TBB`/`TBH` addresses whose jump table has already been recovered,
+    /// so we don't redo the table walk on every repeat execution.
+    recovered_tables: FxHashSet<Address>,
+    /// recovered case targets per table-branch address, exposed so
+    /// downstream coverage accounting sees every arm, not just the one
+    /// taken at runtime
+    table_branch_targets: FxHashMap<Address, FxHashSet<Address>>,
 }
 
 impl RootCauseTrace {
-    pub fn new(trace_file_path: Option<PathBuf>) -> Self {
+    /// `debug` is the `--debug` CLI flag: when set, the interactive
+    /// single-step debugger (see [`Self::enable_debugger`]) is armed from
+    /// the start instead of being constructed with it permanently off.
+    /// Taking it here, rather than leaving `enable_debugger` as an opt-in
+    /// call a caller might never make, is what actually makes the flag
+    /// reach this trace. `dot_output` is its sibling CLI output option,
+    /// see [`Self::post_run`].
+    ///
+    /// Note: this tree has no call site that constructs a `RootCauseTrace`
+    /// at all (the `root_cause`/`exploration` binaries that would wire one
+    /// up live in crates not present here), so `debug` and `dot_output`
+    /// are reachable in principle but currently unexercised by anything
+    /// that actually runs.
+    pub fn new(trace_file_path: Option<PathBuf>, debug: bool, dot_output: bool) -> Self {
         let trace_dir = if let Some(path) = trace_file_path {
             let parent = path.parent().unwrap_or_else(|| &Path::new("."));
             Some(parent.to_path_buf())
@@ -191,9 +770,10 @@ impl RootCauseTrace {
             .build()
             .expect("failed to init capstone");
 
-        RootCauseTrace {
+        let mut trace = RootCauseTrace {
             instructions: FxHashMap::default(),
             edges: FxHashMap::default(),
+            resolved_indirect_edges: FxHashSet::default(),
             reg_state: [0; Register::AMOUNT],
             prev_edge_type: EdgeType::Unknown,
             prev_ins_addr: 0,
@@ -206,7 +786,31 @@ impl RootCauseTrace {
             trace_cnt: 0,
             cs: cs,
             detailed_trace_info: vec![],
+            debugger: None,
+            decode_cache: FxHashMap::default(),
+            recovered_tables: FxHashSet::default(),
+            table_branch_targets: FxHashMap::default(),
+            dot_output,
+        };
+
+        if debug {
+            trace.enable_debugger(false);
         }
+
+        trace
+    }
+
+    /// Recovered jump-table successors for a `TBB`/`TBH` at `pc`, if any
+    /// were statically resolved from the offset table following the
+    /// instruction. Empty/absent until that address has actually executed
+    /// at least once (the table is only walked lazily, from `on_instruction`).
+    pub fn table_branch_targets(&self, pc: Address) -> Option<&FxHashSet<Address>> {
+        self.table_branch_targets.get(&pc)
+    }
+
+    /// Enable the interactive single-step debugger (the `--debug` CLI flag).
+    pub fn enable_debugger(&mut self, trace_only: bool) {
+        self.debugger = Some(Debugger::new(trace_only));
     }
 
     pub fn post_run(
@@ -313,6 +917,46 @@ impl RootCauseTrace {
         trace_dir.pop();
         trace_dir.pop();
 
+        // side-channel artifact with read/write memory accesses still
+        // split apart, see `MemoryDetail`'s doc comment
+        let memory_details: Vec<MemoryDetail> = self
+            .instructions
+            .iter()
+            .filter_map(|(pc, inst)| inst.to_memory_detail(*pc))
+            .collect();
+
+        let mut stream = if is_crash || is_bug {
+            trace_dir.push(format!("crashes/{}-memory-detail.bin", random_number));
+            bufwriter(&trace_dir)
+        } else {
+            trace_dir.push(format!("non_crashes/{}-memory-detail.bin", random_number));
+            bufwriter(&trace_dir)
+        }
+        .context("Unable to open memory detail file")?;
+
+        bincode::serialize_into(&mut stream, &memory_details)
+            .context("serialize memory detail")?;
+
+        trace_dir.pop();
+        trace_dir.pop();
+
+        // optional CLI output: a Graphviz CFG dump alongside the bincode
+        // summary/full artifacts above
+        if self.dot_output {
+            trace_dir.push(if is_crash || is_bug {
+                format!("crashes/{}-cfg.dot", random_number)
+            } else {
+                format!("non_crashes/{}-cfg.dot", random_number)
+            });
+
+            let dot = self.to_dot();
+            bufwriter(&trace_dir)
+                .and_then(|mut f| f.write_all(dot.as_bytes()).context("write all"))?;
+
+            trace_dir.pop();
+            trace_dir.pop();
+        }
+
         self.reset();
         self.trace_cnt += 1;
 
@@ -322,12 +966,257 @@ impl RootCauseTrace {
     fn reset(&mut self) {
         self.instructions.clear();
         self.edges.clear();
+        self.resolved_indirect_edges.clear();
         self.prev_ins_addr = 0;
         self.prev_edge_type = EdgeType::Unknown;
         self.prev_regs_written = vec![];
         self.prev_mnemonic = None;
         self.reg_state = [0; Register::AMOUNT];
         self.detailed_trace_info.clear();
+        self.decode_cache.clear();
+        self.recovered_tables.clear();
+        self.table_branch_targets.clear();
+    }
+
+    /// Disassemble the instruction at `pc`, or return the cached result from
+    /// the last time we saw this address. Repeat executions of the same PC
+    /// (loops, called functions) become a hash lookup instead of a Capstone
+    /// call plus string formatting plus the CPSR->XPSR rewrite and
+    /// `Register::try_from` parsing.
+    fn decode(&mut self, pc: Address) -> DecodedInsn {
+        if let Some(decoded) = self.decode_cache.get(&pc) {
+            return decoded.clone();
+        }
+
+        let decoded = qcontrol()
+            .memory_blocks()
+            .find(|x| x.contains(pc))
+            .and_then(|mem_block| {
+                let off = (pc - mem_block.start) as usize;
+
+                // ARMv7-M thumb2 is a mix of 2 and 4 byte instructions, therefore
+                // we try to disassemble every instruction contained within 4 bytes
+                // and take the first valid inst found
+                let inst = self
+                    .cs
+                    .disasm_all(&mem_block.data[off..(off + 4)], 0)
+                    .ok()?;
+                let inst = inst.iter().next()?;
+
+                let mut regs_written = self
+                    .cs
+                    .insn_detail(&inst)
+                    .and_then(|detail| {
+                        Ok(detail
+                            .regs_write()
+                            .iter()
+                            .filter_map(|&reg_id| self.cs.reg_name(reg_id))
+                            .collect::<Vec<_>>())
+                    })
+                    .unwrap_or(vec![]);
+
+                // capstone doesnt differentiate between xpsr and cpsr. Since we know that
+                // we are only considering Cortex-M, we adjust the name
+                for reg in regs_written.iter_mut() {
+                    if reg.to_uppercase() == "CPSR" {
+                        *reg = "XPSR".to_string();
+                    }
+                }
+
+                let regs_written = regs_written
+                    .iter()
+                    .map(|x| Register::try_from(x.as_str()))
+                    .collect::<Result<Vec<_>>>()
+                    .unwrap_or(vec![]);
+
+                let table_branch = if inst.id().0 == ArmInsn::ARM_INS_TBB as u32 {
+                    Some(TableBranchKind::Byte)
+                } else if inst.id().0 == ArmInsn::ARM_INS_TBH as u32 {
+                    Some(TableBranchKind::Halfword)
+                } else {
+                    None
+                };
+
+                let edge_type = self.get_edge_type(&inst);
+
+                // for a still-unresolved indirect branch, remember which
+                // register operand holds the target address so on_instruction
+                // can feed its observed values into value-range resolution
+                let indirect_operand = (edge_type == EdgeType::Indirect)
+                    .then(|| self.cs.insn_detail(&inst).ok())
+                    .flatten()
+                    .and_then(|detail| match detail.arch_detail() {
+                        capstone::arch::ArchDetail::ArmDetail(inst_detail) => inst_detail
+                            .operands()
+                            .find_map(|op| match op.op_type {
+                                ArmOperandType::Reg(reg_id) => self
+                                    .cs
+                                    .reg_name(reg_id)
+                                    .and_then(|name| Register::try_from(name.as_str()).ok()),
+                                _ => None,
+                            }),
+                        _ => None,
+                    });
+
+                Some(DecodedInsn {
+                    mnemonic: Some(format!(
+                        "{} {}",
+                        inst.mnemonic().unwrap_or(""),
+                        inst.op_str().unwrap_or(""),
+                    )),
+                    edge_type,
+                    regs_written,
+                    is_it: inst.id().0 == ArmInsn::ARM_INS_IT as u32,
+                    table_branch,
+                    indirect_operand,
+                    size: inst.bytes().len() as u8,
+                })
+            })
+            .unwrap_or(DecodedInsn {
+                mnemonic: None,
+                edge_type: EdgeType::Unknown,
+                regs_written: vec![],
+                is_it: false,
+                table_branch: None,
+                indirect_operand: None,
+                size: 0,
+            });
+
+        self.decode_cache.insert(pc, decoded.clone());
+        decoded
+    }
+
+    /// Recover every case target of a `TBB`/`TBH` table branch at `pc` by
+    /// walking the offset table that immediately follows the instruction
+    /// (analogous to a compiler expanding `SwitchTargets` into multiple
+    /// successors), and record a `Edge` for each one so the CFG isn't
+    /// missing every arm except the one taken at runtime.
+    fn recover_table_branch(&mut self, pc: Address, kind: TableBranchKind, edge_type: EdgeType) {
+        let Some(mem_block) = qcontrol().memory_blocks().find(|block| block.contains(pc)) else {
+            return;
+        };
+
+        let entry_size: Address = match kind {
+            TableBranchKind::Byte => 1,
+            TableBranchKind::Halfword => 2,
+        };
+
+        // the 4-byte TBB/TBH encoding reads PC as its own address + 4, and
+        // the table itself starts right there
+        let table_base = pc + 4;
+
+        // bound the table: prefer the smallest already-known branch target
+        // above `pc` (the classic "the table lives between the TB
+        // instruction and its first destination" invariant). The first
+        // time any given table branch fires none of its own case targets
+        // are known yet, so fall back to a capped entry count instead of
+        // the end of the enclosing memory block -- arms past the cap are
+        // still picked up as plain `Edge`s once actually taken, the same
+        // way `update_edges` records any other successor.
+        let table_end = self
+            .edges
+            .keys()
+            .map(|edge| edge.to)
+            .filter(|&to| to > pc)
+            .min()
+            .unwrap_or_else(|| {
+                (table_base + MAX_TABLE_ENTRIES * entry_size)
+                    .min(mem_block.start + mem_block.data.len() as Address)
+            });
+
+        let mut addr = table_base;
+        let mut targets = FxHashSet::default();
+
+        while addr < table_end && mem_block.contains(addr + entry_size - 1) {
+            let rel = (addr - mem_block.start) as usize;
+            let entry = match kind {
+                TableBranchKind::Byte => mem_block.data[rel] as u32,
+                TableBranchKind::Halfword => {
+                    u16::from_le_bytes([mem_block.data[rel], mem_block.data[rel + 1]]) as u32
+                }
+            };
+
+            let target = table_base.wrapping_add(2 * entry);
+            if !mem_block.contains(target) {
+                break;
+            }
+
+            // a genuine case target decodes to a real instruction; once we
+            // walk past the table's real end, further bytes reinterpreted
+            // as entries almost never do
+            if self.decode(target).mnemonic.is_none() {
+                break;
+            }
+
+            targets.insert(target);
+            addr += entry_size;
+        }
+
+        for &target in &targets {
+            self.edges
+                .entry(Edge { from: pc, to: target })
+                .or_insert(EdgeInfo { edge_type, count: 0 });
+        }
+
+        if !targets.is_empty() {
+            self.table_branch_targets.insert(pc, targets);
+        }
+    }
+
+    /// Feed an observed value of an indirect branch's target register into
+    /// that instruction's value-set, and materialize concrete `Edge`s for
+    /// every value observed so far as long as the set stays within
+    /// `INDIRECT_VALUE_SET_CAP` (a tight, discrete range, e.g. vtable/
+    /// function-pointer dispatch). Once it overflows, the register is
+    /// assumed too wide-ranging to trust and no further targets are
+    /// materialized for this instruction; edges materialized before the
+    /// overflow stay in place.
+    fn resolve_indirect_targets(&mut self, pc: Address, value: u32) {
+        let targets = {
+            let Some(inst_data) = self.instructions.get_mut(&pc) else {
+                return;
+            };
+
+            // grows to at most CAP + 1 entries, so the overflow check
+            // below can tell a full set from an overflowed one
+            if inst_data.indirect_observed.len() <= INDIRECT_VALUE_SET_CAP {
+                inst_data.indirect_observed.insert(value);
+            }
+
+            if !inst_data.indirect_min.is_set || value < inst_data.indirect_min.value {
+                inst_data.indirect_min = Value {
+                    is_set: true,
+                    value,
+                };
+            }
+            if !inst_data.indirect_max.is_set || value > inst_data.indirect_max.value {
+                inst_data.indirect_max = Value {
+                    is_set: true,
+                    value,
+                };
+            }
+
+            if inst_data.indirect_observed.len() > INDIRECT_VALUE_SET_CAP {
+                return;
+            }
+
+            inst_data.indirect_observed.clone()
+        };
+
+        // trace_analysis::trace::EdgeType has no separate "resolved" variant,
+        // so speculative targets are recorded as plain Indirect edges, same
+        // as ones update_edges would add once actually taken. Resolved vs.
+        // speculative is still tracked locally in `resolved_indirect_edges`
+        // so `cfg()`/`to_dot()` can tell them apart.
+        for &target in &targets {
+            let edge = Edge { from: pc, to: target };
+
+            self.edges.entry(edge).or_insert(EdgeInfo {
+                edge_type: EdgeType::Indirect,
+                count: 0,
+            });
+            self.resolved_indirect_edges.insert(edge);
+        }
     }
 
     pub fn on_memory_access(
@@ -339,8 +1228,9 @@ impl RootCauseTrace {
         value: USize,
         size: u8,
     ) -> Result<()> {
-        // disregard everything with more than 4 bytes similar to aurora
-        if access_type != AccessType::Write || size > 0x4 {
+        // LDRD/STRD are a doubleword (8 byte) access; still reject anything
+        // wider than that as unexpected rather than silently folding it in
+        if size > 0x8 {
             return Ok(());
         }
 
@@ -348,34 +1238,51 @@ impl RootCauseTrace {
             log::info!("Memory access size 0? {:x}", pc);
         }
 
-        if let Some(inst) = self.instructions.get_mut(&pc) {
-            let mem_data = &mut inst.mem_data;
-            let access = MemoryField {
-                address,
-                size,
-                value,
-            };
+        let access = MemoryField {
+            address,
+            size,
+            value,
+        };
 
-            if mem_data.last_addr.size != 0x0 && mem_data.last_addr.size != access.size {
-                log::info!("Memory operand has different access sizes: {:x}", pc);
-            }
+        // a write into a read-only memory block is suspicious (a read is
+        // expected and fine), flag it on the instruction for the analyzer
+        let write_to_readonly = access_type == AccessType::Write
+            && qcontrol()
+                .memory_blocks()
+                .find(|block| block.contains(address))
+                .map(|block| block.readonly)
+                .unwrap_or(false);
 
-            if mem_data.max_addr.address <= access.address {
-                mem_data.max_addr = access;
-            }
-            if mem_data.min_addr.address >= access.address {
-                mem_data.min_addr = access;
+        if let Some(inst) = self.instructions.get_mut(&pc) {
+            match access_type {
+                AccessType::Write => inst.mem_write.record(access),
+                AccessType::Read => inst.mem_read.record(access),
+                _ => return Ok(()),
             }
-            mem_data.last_addr = access;
 
-            if mem_data.max_value.value <= access.value {
-                mem_data.max_value = access;
-            }
-            if mem_data.min_value.value >= access.value {
-                mem_data.min_value = access;
+            if write_to_readonly && !inst.write_to_readonly {
+                // trace_analysis::trace::Memory has no field for this, so
+                // surface it via the log instead of the serialized trace
+                log::warn!("write to read-only memory at 0x{:x} (pc 0x{:x})", address, pc);
+                inst.write_to_readonly = true;
             }
-            mem_data.last_value = access;
         }
+
+        let hit_memory_watchpoint = access_type == AccessType::Write
+            && self
+                .debugger
+                .as_ref()
+                .map(|debugger| debugger.memory_watchpoints.contains(&address))
+                .unwrap_or(false);
+
+        if hit_memory_watchpoint {
+            let registers: Vec<u32> = (0..Register::AMOUNT)
+                .map(|i| self.reg_state[i])
+                .collect();
+            println!("watchpoint hit: write to 0x{:x} at pc 0x{:x}", address, pc);
+            self.debugger_prompt(pc, &registers)?;
+        }
+
         Ok(())
     }
 
@@ -393,60 +1300,56 @@ impl RootCauseTrace {
 
         let registers = registers.context("failed to obtain registers")?;
 
-        let (mnemonic, edge_type, mut regs_written, is_it) = qcontrol()
-            .memory_blocks()
-            .find(|x| x.contains(pc))
-            .and_then(|mem_block| {
-                let off = (pc - mem_block.start) as usize;
-
-                // ARMv7-M thumb2 is a mix of 2 and 4 byte instructions, therefore
-                // we try to disassemble every instruction contained within 4 bytes
-                // and take the first valid inst found
-                let inst = self
-                    .cs
-                    .disasm_all(&mem_block.data[off..(off + 4)], 0)
-                    .ok()?;
-                let inst = inst.iter().next()?;
-
-                let regs_written = self
-                    .cs
-                    .insn_detail(&inst)
-                    .and_then(|detail| {
-                        Ok(detail
-                            .regs_write()
-                            .iter()
-                            .filter_map(|&reg_id| self.cs.reg_name(reg_id))
-                            .collect::<Vec<_>>())
-                    })
-                    .unwrap_or(vec![]);
+        // debugger hook: runs before any trace-recording logic below
+        if self
+            .debugger
+            .as_mut()
+            .map(|debugger| debugger.should_stop(pc, &registers))
+            .unwrap_or(false)
+        {
+            self.debugger_prompt(pc, &registers)?;
+        }
 
-                Some((
-                    Some(format!(
-                        "{} {}",
-                        inst.mnemonic().unwrap_or(""),
-                        inst.op_str().unwrap_or(""),
-                    )),
-                    self.get_edge_type(&inst),
-                    regs_written,
-                    inst.id().0 == ArmInsn::ARM_INS_IT as u32,
-                ))
-            })
-            .unwrap_or((None, EdgeType::Unknown, vec![], false));
+        let DecodedInsn {
+            mnemonic,
+            edge_type,
+            regs_written,
+            is_it,
+            table_branch,
+            indirect_operand,
+            size,
+        } = self.decode(pc);
+
+        // whether this instruction sits in an active Thumb IT block slot:
+        // an ordinary POP{...,pc}/BX/B here is predicated without carrying
+        // its own condition code on the branch mnemonic, so get_edge_type
+        // alone can't see it
+        let it_block_slot = self.itstate.is_some();
+
+        // advance/consult IT state before feeding this instruction into the
+        // CFG: a predicated instruction whose condition is false this time
+        // never actually executes, so it must not contribute a table-branch
+        // recovery or indirect-target observation below
+        let skip_inst = self.update_itstate(registers[Register::xPSR as usize]);
+
+        // recover jump-table successors the first time we hit this
+        // TBB/TBH, so the CFG gets every case target instead of only the
+        // one taken at runtime
+        if !skip_inst {
+            if let Some(kind) = table_branch {
+                if self.recovered_tables.insert(pc) {
+                    self.recover_table_branch(pc, kind, edge_type);
+                }
+            }
 
-        // capstone doesnt differentiate between xpsr and cpsr. Since we know that
-        // we are only considering Cortex-M, we adjust the name
-        for reg in regs_written.iter_mut() {
-            if reg.to_uppercase() == "CPSR" {
-                *reg = "XPSR".to_string();
+            // resolve indirect branch destinations from the observed value
+            // range of their target register, materializing concrete Edges
+            // once that range is tight enough to trust
+            if let Some(reg) = indirect_operand {
+                self.resolve_indirect_targets(pc, registers[reg as usize]);
             }
         }
 
-        let regs_written = regs_written
-            .iter()
-            .map(|x| Register::try_from(x.as_str()))
-            .collect::<Result<Vec<_>>>()
-            .unwrap_or(vec![]);
-
         // update register state to prevent false positives due to special arm
         // instructions that restore register values and returns
         // e.g.: pop        {r3,r4,r5,r6,r7,pc}
@@ -479,12 +1382,9 @@ impl RootCauseTrace {
         // skip instructions as long as update_itstate returns true
         // we don't have to update anything else except prev_edge_type as we are already
         // in a conditional block so previous instruction should be counted as edge source
-        if self.itstate.is_some() {
-            let skip_inst = self.update_itstate(registers[Register::xPSR as usize]);
-            if skip_inst {
-                self.prev_edge_type = EdgeType::Conditional;
-                return Ok(());
-            }
+        if it_block_slot && skip_inst {
+            self.prev_edge_type = EdgeType::Conditional;
+            return Ok(());
         }
 
         // handle conditional execution
@@ -498,6 +1398,34 @@ impl RootCauseTrace {
             self.init_itstate_str(mnemonic.clone().unwrap_or("".to_string()))?;
         }
 
+        // a predicated control-flow instruction executing inside an IT
+        // block is only conditionally taken -- the other outcome (the
+        // condition being false) would have skipped it entirely -- so
+        // reclassify it as Conditional and record the implicit
+        // fall-through edge for that untaken path
+        let edge_type = if it_block_slot
+            && matches!(
+                edge_type,
+                EdgeType::Direct | EdgeType::Indirect | EdgeType::Return
+            ) {
+            if size > 0 {
+                let next_pc = pc + size as Address;
+                self.edges
+                    .entry(Edge {
+                        from: pc,
+                        to: next_pc,
+                    })
+                    .or_insert(EdgeInfo {
+                        edge_type: EdgeType::Conditional,
+                        count: 0,
+                    });
+            }
+
+            EdgeType::Conditional
+        } else {
+            edge_type
+        };
+
         match edge_type {
             // regular edges are being handled after they have been executed
             EdgeType::Regular => self.prev_mnemonic = mnemonic,
@@ -516,102 +1444,16 @@ impl RootCauseTrace {
         Ok(())
     }
 
-    // could use this func if QEMU would update xPSR correctly
-    fn init_itstate(&mut self, xPSR: u32, mnemonic: String) {
-        // [26:25] = IT[7:6], [15:10] = IT[5:0]
-        let itstate = ((xPSR >> 25) & 3) << 5 | ((xPSR >> 10) & 0x3f);
-
-        let base_condition = (itstate >> 5) & 7;
-        let sz = (itstate & 0x1f).count_ones();
-        println!(
-            "Handle it state:  {} {:32b} {} {} {}",
-            mnemonic, xPSR, base_condition, itstate, sz
-        );
-    }
-
-    // todo: Implement sth like register index to make this hardcoding go away
-    // example: ite eq => Condition is eq, condition should be true for first instruction
-    // following it instruction (t) and false for the second (e)
+    // ITAdvance + condition check for the current in-block instruction.
+    // returns whether the instruction should be skipped (condition false).
     fn update_itstate(&mut self, xPSR: u32) -> bool {
-        let mut should_skip_inst = false;
-        let bit_set = |val: u32, pos: u32| -> bool { (val & (1 << pos)) != 0 };
-        let bits_equal =
-            |val: u32, pos1: u32, pos2: u32| -> bool { bit_set(val, pos1) == bit_set(val, pos2) };
-        if let Some(ref mut itstate) = self.itstate {
-            let condition_set = match itstate.condition {
-                // equal, Z = 1
-                ConditionCode::EQ => bit_set(xPSR, FlagBits::Z.to_bit_index() as u32),
-                // Not equal, Z = 0
-                ConditionCode::NE => !bit_set(xPSR, FlagBits::Z.to_bit_index() as u32),
-                // Higher or same, unsigned C = 1
-                ConditionCode::CS => bit_set(xPSR, FlagBits::C.to_bit_index() as u32),
-                // Lower, unsigned C = 0
-                ConditionCode::CC => !bit_set(xPSR, FlagBits::C.to_bit_index() as u32),
-                // Negative, N = 1
-                ConditionCode::MI => bit_set(xPSR, FlagBits::N.to_bit_index() as u32),
-                // Positive or zero , N = 0
-                ConditionCode::PL => !bit_set(xPSR, FlagBits::N.to_bit_index() as u32),
-                // Overflow, V = 1
-                ConditionCode::VS => bit_set(xPSR, FlagBits::V.to_bit_index() as u32),
-                // No overflow, V = 0
-                ConditionCode::VC => !bit_set(xPSR, FlagBits::V.to_bit_index() as u32),
-                // Higher, unsigned, C = 1 && Z = 0
-                ConditionCode::HI => {
-                    bit_set(xPSR, FlagBits::C.to_bit_index() as u32)
-                        && !bit_set(xPSR, FlagBits::Z.to_bit_index() as u32)
-                }
-                // Lower or same, unsigned, C = 0 || Z = 1
-                ConditionCode::LS => {
-                    !bit_set(xPSR, FlagBits::C.to_bit_index() as u32)
-                        || bit_set(xPSR, FlagBits::Z.to_bit_index() as u32)
-                }
-                // Greater equal, signed, N = V
-                ConditionCode::GE => bits_equal(
-                    xPSR,
-                    FlagBits::N.to_bit_index() as u32,
-                    FlagBits::V.to_bit_index() as u32,
-                ),
-                // Less than, signed, N != V
-                ConditionCode::LT => !bits_equal(
-                    xPSR,
-                    FlagBits::N.to_bit_index() as u32,
-                    FlagBits::V.to_bit_index() as u32,
-                ),
-                // Greater than, signed, Z = 0 && N = V
-                ConditionCode::GT => {
-                    !bit_set(xPSR, FlagBits::Z.to_bit_index() as u32)
-                        && bits_equal(
-                            xPSR,
-                            FlagBits::N.to_bit_index() as u32,
-                            FlagBits::V.to_bit_index() as u32,
-                        )
-                }
-                // Less than or equal, signed, Z = 1 && N != V
-                ConditionCode::LE => {
-                    bit_set(xPSR, FlagBits::Z.to_bit_index() as u32)
-                        && !bits_equal(
-                            xPSR,
-                            FlagBits::N.to_bit_index() as u32,
-                            FlagBits::V.to_bit_index() as u32,
-                        )
-                }
-                _ => unimplemented!(),
-            };
-
-            if let Some(exec_if_condition_set) = itstate.state.pop() {
-                if exec_if_condition_set {
-                    // execute if condition is set, so skip if not set
-                    should_skip_inst = !condition_set;
-                } else {
-                    // execute if condition not set, so skip if set
-                    should_skip_inst = condition_set;
-                }
+        let itstate = match self.itstate {
+            Some(itstate) => itstate,
+            None => return false,
+        };
 
-                if itstate.state.len() == 0 {
-                    self.itstate = None;
-                }
-            }
-        }
+        let should_skip_inst = !condition_passes(itstate.condition(), xPSR);
+        self.itstate = itstate.advance();
 
         should_skip_inst
     }
@@ -624,27 +1466,12 @@ impl RootCauseTrace {
             unimplemented!();
         }
 
-        let condition_code = ConditionCode::try_from(parts[1])?;
+        let firstcond = condition_code_value(ConditionCode::try_from(parts[1])?);
 
-        let mut conditions = vec![];
-        for c in parts[0].chars().skip(1) {
-            if c == 't' {
-                conditions.push(true);
-            } else {
-                conditions.push(false);
-            }
-        }
-        // reverse vector as conditions are evaluated from msb to lsb
-        conditions.reverse();
+        // suffix letters after the leading "it", e.g. "et" in "itet"
+        let suffix: Vec<bool> = parts[0].chars().skip(2).map(|c| c == 't').collect();
 
-        /*
-        println!(
-            "Itstate: {}, {:?}, {:?}",
-            mnemonic, condition_code, conditions
-        );
-        */
-
-        self.itstate = Some(ItState::new(condition_code, conditions));
+        self.itstate = Some(ItState::from_condition_and_suffix(firstcond, &suffix));
 
         Ok(())
     }
@@ -732,6 +1559,18 @@ impl RootCauseTrace {
         Ok(())
     }
 
+    /// Fold the raw edge map into a basic-block `Cfg`.
+    pub fn cfg(&self) -> Cfg {
+        Cfg::build(&self.edges, self.first_address, &self.resolved_indirect_edges)
+    }
+
+    /// Export the CFG as a Graphviz DOT graph: a visual root-cause artifact
+    /// alongside the bincode summary/full traces already written by
+    /// `post_run`.
+    pub fn to_dot(&self) -> String {
+        self.cfg().to_dot(&self.instructions)
+    }
+
     fn get_edge_type(&self, inst: &capstone::Insn) -> EdgeType {
         match inst.id().0 {
             id if id == ArmInsn::ARM_INS_BX as u32
@@ -745,9 +1584,7 @@ impl RootCauseTrace {
                 || id == ArmInsn::ARM_INS_B as u32
                 //|| id == ArmInsn::ARM_INS_BIC as u32
                 || id == ArmInsn::ARM_INS_CBZ as u32
-                || id == ArmInsn::ARM_INS_CBNZ as u32
-                || id == ArmInsn::ARM_INS_TBH as u32
-                || id == ArmInsn::ARM_INS_TBB as u32 =>
+                || id == ArmInsn::ARM_INS_CBNZ as u32 =>
             {
                 if let Ok(details) = self.cs.insn_detail(&inst) {
                     if let capstone::arch::ArchDetail::ArmDetail(inst_detail) =
@@ -773,9 +1610,313 @@ impl RootCauseTrace {
                 EdgeType::Unknown
             }
 
+            // TBB/TBH dispatch through a static offset table recovered by
+            // recover_table_branch, so (unlike a plain `BX reg`) every case
+            // target is known ahead of time: classify by condition code only
+            // and treat the register index operand as resolved rather than
+            // falling back to Indirect.
+            id if id == ArmInsn::ARM_INS_TBB as u32 || id == ArmInsn::ARM_INS_TBH as u32 => {
+                if let Ok(details) = self.cs.insn_detail(&inst) {
+                    if let capstone::arch::ArchDetail::ArmDetail(inst_detail) =
+                        details.arch_detail()
+                    {
+                        if inst_detail.cc() != capstone::arch::arm::ArmCC::ARM_CC_AL {
+                            return EdgeType::Conditional;
+                        }
+                    }
+                }
+                EdgeType::Direct
+            }
+
             id if id == ArmInsn::ARM_INS_SVC as u32 => EdgeType::Syscall,
 
             _ => EdgeType::Regular,
         }
     }
+
+    /// command-REPL prompt for the interactive debugger, entered when a
+    /// breakpoint/watchpoint fires. Blocks on stdin until `continue`/`step`.
+    fn debugger_prompt(&mut self, pc: Address, registers: &[u32]) -> Result<()> {
+        loop {
+            print!("(hoedur-dbg 0x{:x}) ", pc);
+            io::stdout().flush().ok();
+
+            let mut line = String::new();
+            if io::stdin().read_line(&mut line).context("read debugger command")? == 0 {
+                return Ok(());
+            }
+
+            let line = line.trim();
+            let command = if line.is_empty() {
+                self.debugger.as_ref().and_then(|d| d.last_command.clone())
+            } else {
+                Some(line.to_string())
+            };
+
+            let Some(command) = command else {
+                continue;
+            };
+
+            if let Some(debugger) = self.debugger.as_mut() {
+                debugger.last_command = Some(command.clone());
+            }
+
+            let mut parts = command.split_whitespace();
+            match parts.next() {
+                Some("c") | Some("continue") => return Ok(()),
+                Some("s") | Some("step") => {
+                    let n: u64 = parts.next().and_then(|n| n.parse().ok()).unwrap_or(1);
+                    if let Some(debugger) = self.debugger.as_mut() {
+                        debugger.steps_remaining = n;
+                    }
+                    return Ok(());
+                }
+                Some("b") | Some("break") => match parts.next().and_then(parse_address) {
+                    Some(addr) => {
+                        if let Some(debugger) = self.debugger.as_mut() {
+                            debugger.breakpoints.insert(addr);
+                        }
+                        println!("breakpoint set at 0x{:x}", addr);
+                    }
+                    None => println!("usage: break <addr>"),
+                },
+                Some("clear") => match parts.next().and_then(parse_address) {
+                    Some(addr) => {
+                        if let Some(debugger) = self.debugger.as_mut() {
+                            debugger.breakpoints.remove(&addr);
+                        }
+                    }
+                    None => println!("usage: clear <addr>"),
+                },
+                Some("watch") => match parts.next() {
+                    Some("mem") => match parts.next().and_then(parse_address) {
+                        Some(addr) => {
+                            if let Some(debugger) = self.debugger.as_mut() {
+                                debugger.memory_watchpoints.insert(addr);
+                            }
+                            println!("watching writes to 0x{:x}", addr);
+                        }
+                        None => println!("usage: watch mem <addr>"),
+                    },
+                    Some("reg") => {
+                        let reg = parts.next().and_then(|r| Register::try_from(r).ok());
+                        let min = parts.next().and_then(parse_address);
+                        let max = parts.next().and_then(parse_address);
+
+                        match (reg, min, max) {
+                            (Some(register), Some(min), Some(max)) => {
+                                if let Some(debugger) = self.debugger.as_mut() {
+                                    debugger
+                                        .register_watchpoints
+                                        .push(RegisterWatchpoint { register, min, max });
+                                }
+                            }
+                            _ => println!("usage: watch reg <register> <min> <max>"),
+                        }
+                    }
+                    _ => println!("usage: watch mem <addr> | watch reg <register> <min> <max>"),
+                },
+                Some("regs") => {
+                    for name in Register::printable().iter().map(ToString::to_string) {
+                        if let Ok(reg) = Register::try_from(name.as_str()) {
+                            println!("{} = 0x{:x}", name, registers[reg as usize]);
+                        }
+                    }
+                }
+                Some("disas") => self.print_disassembly(pc),
+                Some("help") | Some("h") => {
+                    println!(
+                        "commands: step [n], continue, break <addr>, clear <addr>, \
+                         watch mem <addr>, watch reg <reg> <min> <max>, regs, disas"
+                    );
+                }
+                Some(cmd) => println!("unknown command: {cmd} (try 'help')"),
+                None => {}
+            }
+        }
+    }
+
+    fn print_disassembly(&self, pc: Address) {
+        let Some(mem_block) = qcontrol().memory_blocks().find(|block| block.contains(pc)) else {
+            return;
+        };
+
+        let off = (pc - mem_block.start) as usize;
+        let end = (off + 4).min(mem_block.data.len());
+
+        if let Ok(insns) = self.cs.disasm_all(&mem_block.data[off..end], pc as u64) {
+            if let Some(insn) = insns.iter().next() {
+                println!(
+                    "0x{:x}: {} {}",
+                    pc,
+                    insn.mnemonic().unwrap_or(""),
+                    insn.op_str().unwrap_or(""),
+                );
+            }
+        }
+    }
+}
+
+fn parse_address(s: &str) -> Option<Address> {
+    match s.strip_prefix("0x") {
+        Some(hex) => Address::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod cfg_tests {
+    use super::*;
+
+    fn edges(pairs: &[(Address, Address, EdgeType)]) -> FxHashMap<Edge, EdgeInfo> {
+        pairs
+            .iter()
+            .map(|&(from, to, edge_type)| {
+                (
+                    Edge { from, to },
+                    EdgeInfo {
+                        edge_type,
+                        count: 1,
+                    },
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn straight_line_run_merges_into_one_block() {
+        let edges = edges(&[
+            (0x104, 0x108, EdgeType::Regular),
+            (0x104, 0x200, EdgeType::Conditional),
+        ]);
+
+        let cfg = Cfg::build(&edges, 0x104, &FxHashSet::default());
+
+        let block = cfg.block(0x104).expect("fallthrough block exists");
+        assert_eq!(block.instructions, vec![0x104, 0x108]);
+        assert_eq!(block.successors.len(), 1);
+        assert_eq!(block.successors[0].0, 0x200);
+        assert!(matches!(block.successors[0].1, EdgeType::Conditional));
+
+        let target = cfg.block(0x200).expect("conditional target is its own block");
+        assert_eq!(target.instructions, vec![0x200]);
+        assert!(target.predecessors.contains(&0x104));
+
+        assert_eq!(cfg.block_of(0x108), Some(0x104));
+    }
+
+    #[test]
+    fn address_with_multiple_predecessors_starts_its_own_block() {
+        let edges = edges(&[
+            (0x100, 0x104, EdgeType::Regular),
+            (0x104, 0x200, EdgeType::Regular),
+            (0x300, 0x200, EdgeType::Regular),
+        ]);
+
+        let cfg = Cfg::build(&edges, 0x100, &FxHashSet::default());
+
+        // 0x200 has two distinct predecessors (0x104 and 0x300), so it must
+        // start its own block even though 0x104 -> 0x200 is a plain Regular
+        // edge that would otherwise be merged
+        assert_eq!(cfg.block_of(0x200), Some(0x200));
+        assert_ne!(cfg.block_of(0x104), cfg.block_of(0x200));
+    }
+
+    #[test]
+    fn resolved_indirect_successors_are_tracked_separately() {
+        let edges = edges(&[
+            (0x300, 0x400, EdgeType::Indirect),
+            (0x300, 0x500, EdgeType::Indirect),
+        ]);
+        let mut resolved_indirect = FxHashSet::default();
+        resolved_indirect.insert(Edge {
+            from: 0x300,
+            to: 0x400,
+        });
+
+        let cfg = Cfg::build(&edges, 0x300, &resolved_indirect);
+
+        let block = cfg.block(0x300).expect("branch block exists");
+        assert!(block.resolved_indirect_successors.contains(&0x400));
+        assert!(!block.resolved_indirect_successors.contains(&0x500));
+    }
+}
+
+#[cfg(test)]
+mod itstate_tests {
+    use super::*;
+
+    fn xpsr_with_flags(n: bool, z: bool, c: bool, v: bool) -> u32 {
+        let mut xpsr = 0u32;
+        for (flag, set) in [
+            (FlagBits::N, n),
+            (FlagBits::Z, z),
+            (FlagBits::C, c),
+            (FlagBits::V, v),
+        ] {
+            if set {
+                xpsr |= 1 << flag.to_bit_index();
+            }
+        }
+        xpsr
+    }
+
+    #[test]
+    fn condition_passes_eq_ne() {
+        let z_set = xpsr_with_flags(false, true, false, false);
+        let z_clear = xpsr_with_flags(false, false, false, false);
+
+        assert!(condition_passes(0b0000, z_set)); // EQ
+        assert!(!condition_passes(0b0000, z_clear));
+        assert!(!condition_passes(0b0001, z_set)); // NE
+        assert!(condition_passes(0b0001, z_clear));
+    }
+
+    #[test]
+    fn condition_passes_ge_lt() {
+        let n_eq_v = xpsr_with_flags(true, false, false, true);
+        let n_ne_v = xpsr_with_flags(true, false, false, false);
+
+        assert!(condition_passes(0b1010, n_eq_v)); // GE
+        assert!(!condition_passes(0b1010, n_ne_v));
+        assert!(condition_passes(0b1011, n_ne_v)); // LT
+        assert!(!condition_passes(0b1011, n_eq_v));
+    }
+
+    #[test]
+    fn condition_passes_al_and_reserved_always_true() {
+        let all_clear = xpsr_with_flags(false, false, false, false);
+        let all_set = xpsr_with_flags(true, true, true, true);
+
+        assert!(condition_passes(0b1110, all_clear)); // AL
+        assert!(condition_passes(0b1111, all_set)); // reserved/NV
+    }
+
+    #[test]
+    fn itstate_single_instruction_block_ends_immediately() {
+        let cond = condition_code_value(ConditionCode::GT);
+        let it = ItState::from_condition_and_suffix(cond, &[]);
+
+        assert_eq!(it.condition(), cond);
+        assert!(it.advance().is_none());
+    }
+
+    #[test]
+    fn itstate_itte_block_advances_then_inverts_for_else() {
+        let eq = condition_code_value(ConditionCode::EQ);
+        let ne = condition_code_value(ConditionCode::NE);
+
+        // ITTE EQ: the first and second in-block instructions are EQ, the
+        // third (else) instruction is the inverted condition NE
+        let it = ItState::from_condition_and_suffix(eq, &[true, false]);
+        assert_eq!(it.condition(), eq);
+
+        let it = it.advance().expect("then-instruction still in the block");
+        assert_eq!(it.condition(), eq);
+
+        let it = it.advance().expect("else-instruction still in the block");
+        assert_eq!(it.condition(), ne);
+
+        assert!(it.advance().is_none());
+    }
 }
@@ -26,11 +26,36 @@ impl ExplorationCoverage {
     }
 }
 
+/// Result of trying to save a deduplicated exploration output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Saved {
+    /// content digest wasn't seen before, file was written
+    New,
+    /// content digest already known, file write was skipped
+    Duplicate,
+}
+
+/// Content hash of an `InputFile`'s serialized bytes, used as the
+/// deduplication key (mirrors the "known chunk" digest sets backup crates
+/// use to skip storing data they've already seen).
+fn content_digest(input: &InputFile) -> Result<u64> {
+    let mut bytes = Vec::with_capacity(input.len());
+    input
+        .write_to(&mut bytes)
+        .context("failed to serialize input for digest")?;
+
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    Ok(hasher.finish())
+}
+
 pub struct ExplorationMode {
     output_dir: PathBuf,
     unique_crashes: usize,
     // not crashing
     unique_inputs: usize,
+    crash_digests: FxHashSet<u64>,
+    non_crash_digests: FxHashSet<u64>,
 }
 
 fn create_dirs(output_dir: &PathBuf) -> Result<()> {
@@ -59,6 +84,8 @@ impl ExplorationMode {
             output_dir,
             unique_crashes: 0,
             unique_inputs: 0,
+            crash_digests: FxHashSet::default(),
+            non_crash_digests: FxHashSet::default(),
         })
     }
 
@@ -74,7 +101,12 @@ impl ExplorationMode {
         self.unique_inputs
     }
 
-    pub fn save_crash(&mut self, f: &InputFile) -> Result<()> {
+    pub fn save_crash(&mut self, f: &InputFile) -> Result<Saved> {
+        let digest = content_digest(f)?;
+        if !self.crash_digests.insert(digest) {
+            return Ok(Saved::Duplicate);
+        }
+
         let crash_path = self
             .output_dir
             .join(format!("exploration/crashes/input-{}.bin", f.id()));
@@ -82,11 +114,17 @@ impl ExplorationMode {
         self.unique_crashes += 1;
 
         let writer = bufwriter(&crash_path).context("unable to create writer for crash path")?;
+        f.write_to(writer).context("failed to write crashing input")?;
 
-        return f.write_to(writer).context("failed to write crashing input");
+        Ok(Saved::New)
     }
 
-    pub fn save_input(&mut self, f: &InputFile) -> Result<()> {
+    pub fn save_input(&mut self, f: &InputFile) -> Result<Saved> {
+        let digest = content_digest(f)?;
+        if !self.non_crash_digests.insert(digest) {
+            return Ok(Saved::Duplicate);
+        }
+
         let non_crash_path = self
             .output_dir
             .join(format!("exploration/non_crashes/input-{}.bin", f.id()));
@@ -95,7 +133,8 @@ impl ExplorationMode {
             bufwriter(&non_crash_path).context("unable to create writer for crash path")?;
 
         self.unique_inputs += 1;
+        f.write_to(writer).context("failed to write crashing input")?;
 
-        return f.write_to(writer).context("failed to write crashing input");
+        Ok(Saved::New)
     }
 }
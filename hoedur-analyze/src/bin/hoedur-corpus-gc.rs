@@ -0,0 +1,352 @@
+use std::{
+    fs,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use archive::Archive;
+use clap::Parser;
+use common::{
+    fs::decoder,
+    log::{init_log, LOG_INFO},
+    FxHashSet,
+};
+use fuzzer::{CorpusEntry, CorpusEntryKind};
+use modeling::hardware::WriteTo;
+
+const MIN_CHUNK_SIZE: usize = 2 * 1024;
+const AVG_CHUNK_SIZE: usize = 8 * 1024;
+const MAX_CHUNK_SIZE: usize = 64 * 1024;
+
+// gear-hash style rolling hash: shift in one byte of hash table lookup per
+// input byte and cut the chunk whenever the low bits match the mask, giving
+// content-defined (not offset-defined) chunk boundaries.
+const MASK: u64 = (1 << (AVG_CHUNK_SIZE.ilog2())) - 1;
+
+/// Content digest of a chunk, used as its key in the chunk store.
+///
+/// There is no archive-crate chunk store to hook into yet, so this tool
+/// owns a minimal one of its own: a flat directory of `<digest>.bin` blobs,
+/// keyed by the same `DefaultHasher` digest `fuzzer::exploration` already
+/// uses for whole-input dedup.
+///
+/// This does *not* shrink any corpus archive today: `archive::Archive`'s
+/// writer has no concept of chunk references, so every archive keeps
+/// storing each input whole on top of whatever this tool writes to
+/// `chunk_store` -- net disk usage for a given corpus goes up, not down,
+/// until the archive crate gains a chunk-reference entry kind and a writer
+/// that uses it. Until then, `main` reports the size this *would* free up
+/// so that work is motivated by a real number instead of an assumption.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ChunkDigest(u64);
+
+impl ChunkDigest {
+    fn of(chunk: &[u8]) -> Self {
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        chunk.hash(&mut hasher);
+        ChunkDigest(hasher.finish())
+    }
+
+    fn file_name(&self) -> String {
+        format!("{:016x}.bin", self.0)
+    }
+}
+
+#[derive(Parser, Debug)]
+#[command(name = "hoedur-corpus-gc")]
+struct Arguments {
+    #[arg(long, default_value = LOG_INFO)]
+    log_config: PathBuf,
+
+    /// Corpus archive(s) whose inputs are still live; chunks referenced by
+    /// these archives are kept (and written to the chunk store if missing)
+    #[arg(required = true)]
+    corpus_archive: Vec<PathBuf>,
+
+    /// Chunk store to populate and garbage-collect
+    #[arg(long)]
+    chunk_store: PathBuf,
+}
+
+/// Content-defined chunk boundaries for `data`, using a gear-hash rolling
+/// hash with min/max clamps (mirrors the Rabin/gear chunkers used by
+/// content-addressed backup stores).
+pub fn chunk_boundaries(data: &[u8]) -> Vec<std::ops::Range<usize>> {
+    let mut boundaries = Vec::new();
+    let mut start = 0;
+    let mut hash: u64 = 0;
+
+    for (i, &byte) in data.iter().enumerate() {
+        let len = i - start + 1;
+
+        hash = (hash << 1).wrapping_add(GEAR_TABLE[byte as usize]);
+
+        let at_max = len >= MAX_CHUNK_SIZE;
+        let boundary_hit = len >= MIN_CHUNK_SIZE && (hash & MASK) == 0;
+
+        if boundary_hit || at_max {
+            boundaries.push(start..i + 1);
+            start = i + 1;
+            hash = 0;
+        }
+    }
+
+    if start < data.len() {
+        boundaries.push(start..data.len());
+    }
+
+    boundaries
+}
+
+/// Flat directory of content-addressed chunk blobs.
+struct ChunkStore {
+    dir: PathBuf,
+}
+
+impl ChunkStore {
+    fn open(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create chunk store dir {dir:?}"))?;
+
+        Ok(ChunkStore {
+            dir: dir.to_owned(),
+        })
+    }
+
+    fn path_for(&self, digest: ChunkDigest) -> PathBuf {
+        self.dir.join(digest.file_name())
+    }
+
+    /// Size in bytes of the chunk stored under `digest`.
+    fn chunk_size(&self, digest: ChunkDigest) -> Result<u64> {
+        let path = self.path_for(digest);
+        fs::metadata(&path)
+            .map(|meta| meta.len())
+            .with_context(|| format!("Failed to stat chunk {path:?}"))
+    }
+
+    /// Write `chunk` under its digest, unless already stored. Returns
+    /// whether the chunk was newly written.
+    fn insert(&self, digest: ChunkDigest, chunk: &[u8]) -> Result<bool> {
+        let path = self.path_for(digest);
+        if path.is_file() {
+            return Ok(false);
+        }
+
+        fs::write(&path, chunk).with_context(|| format!("Failed to write chunk {path:?}"))?;
+        Ok(true)
+    }
+
+    /// Remove every stored chunk whose digest isn't in `referenced`.
+    /// Returns the number of chunks removed.
+    fn prune_unreferenced(&self, referenced: &FxHashSet<ChunkDigest>) -> Result<usize> {
+        let mut removed = 0;
+
+        for entry in fs::read_dir(&self.dir)
+            .with_context(|| format!("Failed to read chunk store dir {:?}", self.dir))?
+        {
+            let entry = entry?;
+            let digest = entry
+                .file_name()
+                .to_str()
+                .and_then(|name| name.strip_suffix(".bin"))
+                .and_then(|name| u64::from_str_radix(name, 16).ok())
+                .map(ChunkDigest);
+
+            let keep = digest.is_some_and(|digest| referenced.contains(&digest));
+            if !keep {
+                fs::remove_file(entry.path())
+                    .with_context(|| format!("Failed to remove chunk {:?}", entry.path()))?;
+                removed += 1;
+            }
+        }
+
+        Ok(removed)
+    }
+}
+
+/// Bookkeeping returned by [`populate_and_collect_referenced`]: the chunk
+/// digests still referenced, plus enough size information to report how
+/// much this tool's chunk store duplicates vs. would save if the archive
+/// crate actually wrote inputs in deduplicated chunks (see `ChunkDigest`'s
+/// doc comment).
+struct ScanResult {
+    referenced: FxHashSet<ChunkDigest>,
+    /// total bytes of every live input, counted once per corpus archive
+    /// that references it (i.e. what's on disk today: each archive still
+    /// stores its inputs whole, on top of this tool's chunk store).
+    input_bytes_total: u64,
+    /// sum of unique chunk sizes across `referenced` -- the size the chunk
+    /// store itself uses, and what each archive's input storage *would*
+    /// shrink to if it referenced chunks instead of embedding them.
+    unique_chunk_bytes: u64,
+}
+
+/// Chunk every live input across `corpus_archives`, writing any
+/// not-yet-seen chunk into `chunk_store`. This does not reduce disk usage
+/// today -- see the doc comment on `ChunkDigest` -- so it
+/// also tracks enough size information for `main` to report the would-be
+/// savings from wiring chunk-referencing into the archive crate's writer.
+fn populate_and_collect_referenced(
+    corpus_archives: &[PathBuf],
+    chunk_store: &ChunkStore,
+) -> Result<ScanResult> {
+    let mut referenced = FxHashSet::default();
+    let mut input_bytes_total = 0u64;
+
+    for path in corpus_archives {
+        log::info!("Scanning corpus archive {} ...", path.display());
+        let mut archive =
+            Archive::from_reader(decoder(path).context("Failed to load corpus archive")?);
+
+        for entry in archive.iter::<CorpusEntryKind>()? {
+            let mut entry = entry?;
+
+            if let Some(CorpusEntryKind::InputFile(_)) = entry.kind() {
+                if let CorpusEntry::InputFile { input, .. } = entry.parse_entry().unwrap()? {
+                    let mut bytes = Vec::with_capacity(input.write_size()?);
+                    input
+                        .write_to(&mut bytes)
+                        .context("Failed to serialize input for chunking")?;
+
+                    input_bytes_total += bytes.len() as u64;
+
+                    for range in chunk_boundaries(&bytes) {
+                        let chunk = &bytes[range];
+                        let digest = ChunkDigest::of(chunk);
+
+                        if chunk_store.insert(digest, chunk)? {
+                            log::debug!("stored new chunk {:016x}", digest.0);
+                        }
+
+                        referenced.insert(digest);
+                    }
+                }
+            }
+        }
+    }
+
+    let unique_chunk_bytes = referenced
+        .iter()
+        .map(|digest| chunk_store.chunk_size(*digest))
+        .sum::<Result<u64>>()?;
+
+    Ok(ScanResult {
+        referenced,
+        input_bytes_total,
+        unique_chunk_bytes,
+    })
+}
+
+fn main() -> Result<()> {
+    let opt = Arguments::parse();
+
+    init_log(&opt.log_config)?;
+    log::trace!("Args: {:#?}", opt);
+
+    let chunk_store = ChunkStore::open(&opt.chunk_store).context("Failed to open chunk store")?;
+
+    let scan = populate_and_collect_referenced(&opt.corpus_archive, &chunk_store)?;
+    log::info!("{} chunks referenced by live corpora", scan.referenced.len());
+
+    let removed = chunk_store
+        .prune_unreferenced(&scan.referenced)
+        .context("Failed to prune chunk store")?;
+    log::info!("removed {removed} chunks no longer referenced by any corpus archive");
+
+    // today this is purely additive disk usage (see ChunkDigest docs above),
+    // so report what wiring chunk-references into the archive writer would
+    // actually be worth, rather than implying this run saved it already
+    let would_save = scan.input_bytes_total.saturating_sub(scan.unique_chunk_bytes);
+    log::info!(
+        "chunk store holds {} unique bytes for {} input bytes across all scanned archives \
+         ({} would be saved if corpus archives referenced chunks instead of embedding inputs)",
+        scan.unique_chunk_bytes,
+        scan.input_bytes_total,
+        would_save,
+    );
+
+    Ok(())
+}
+
+// precomputed random 64-bit constants, one per byte value; standard gear-hash
+// table construction (see e.g. FastCDC / restic's chunker).
+static GEAR_TABLE: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut seed: u64 = 0x9E3779B97F4A7C15;
+    let mut i = 0;
+    while i < 256 {
+        // splitmix64
+        seed = seed.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = seed;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunk_boundaries_cover_input_contiguously() {
+        let data = vec![0u8; MAX_CHUNK_SIZE * 3 + 17];
+        let boundaries = chunk_boundaries(&data);
+
+        assert!(!boundaries.is_empty());
+
+        let mut expected_start = 0;
+        for range in &boundaries {
+            assert_eq!(range.start, expected_start);
+            assert!(range.len() <= MAX_CHUNK_SIZE);
+            expected_start = range.end;
+        }
+        assert_eq!(expected_start, data.len());
+    }
+
+    #[test]
+    fn chunk_boundaries_respect_max_size() {
+        // all-zero input never hits the content-defined boundary on its own,
+        // so every chunk should be cut by the max-size clamp instead
+        let data = vec![0u8; MAX_CHUNK_SIZE * 4];
+        let boundaries = chunk_boundaries(&data);
+
+        for range in &boundaries {
+            assert_eq!(range.len(), MAX_CHUNK_SIZE);
+        }
+    }
+
+    #[test]
+    fn chunk_boundaries_are_content_defined() {
+        // an inserted byte should only perturb chunk boundaries near it, not
+        // the whole file -- the hallmark of content-defined (vs. fixed
+        // offset) chunking. The data is pseudo-random so that the rolling
+        // hash actually hits the mask before the insertion point.
+        let mut data = vec![0u8; MAX_CHUNK_SIZE * 2];
+        let mut state: u32 = 0x1234_5678;
+        for byte in data.iter_mut() {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12_345);
+            *byte = (state >> 16) as u8;
+        }
+
+        let original = chunk_boundaries(&data);
+
+        let midpoint = data.len() / 2;
+        data.insert(midpoint, 0xAB);
+        let shifted = chunk_boundaries(&data);
+
+        let prefix_matches = original
+            .iter()
+            .take_while(|range| range.end < midpoint)
+            .count();
+        assert!(
+            prefix_matches > 0,
+            "expected at least the chunks entirely before the insertion point to survive unchanged"
+        );
+        assert_eq!(shifted[..prefix_matches], original[..prefix_matches]);
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    fs,
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use anyhow::{Context, Result};
+use archive::Archive;
+use clap::Parser;
+use common::{
+    fs::decoder,
+    log::{init_log, LOG_INFO},
+};
+use fuzzer::{CorpusEntry, CorpusEntryKind};
+use hoedur::coverage::CoverageReport;
+use hoedur_analyze::{crash_reason_for, shortest_reproducers};
+use modeling::hardware::WriteTo;
+use modeling::input::InputId;
+
+#[derive(Parser, Debug)]
+#[command(name = "hoedur-corpus-shell")]
+struct Arguments {
+    #[arg(long, default_value = LOG_INFO)]
+    log_config: PathBuf,
+
+    /// Corpus archive to open
+    corpus_archive: PathBuf,
+
+    /// Coverage report used by `stat` and `reproducer` to join crash reasons
+    #[arg(long)]
+    report: Option<PathBuf>,
+}
+
+/// Interactive REPL over a corpus archive: the archive reader and the
+/// optional coverage report are opened once, at startup. No decoded entries
+/// or catalog are kept between commands: `ls`, `stat`, `cat`, `extract` and
+/// `reproducer` each iterate the archive's entries again from that reader.
+struct CorpusShell {
+    archive: Archive<Box<dyn io::Read>>,
+    report: Option<CoverageReport>,
+}
+
+impl CorpusShell {
+    fn open(corpus_archive: PathBuf, report: Option<PathBuf>) -> Result<Self> {
+        let reader: Box<dyn io::Read> =
+            Box::new(decoder(&corpus_archive).context("Failed to load corpus archive")?);
+        let archive = Archive::from_reader(reader);
+
+        let report = report
+            .map(|path| {
+                CoverageReport::load_from(&path)
+                    .with_context(|| format!("Failed to load coverage report {path:?}"))
+            })
+            .transpose()?;
+
+        Ok(CorpusShell { archive, report })
+    }
+
+    fn ls(&mut self) -> Result<()> {
+        for entry in self.archive.iter::<CorpusEntryKind>()? {
+            let mut entry = entry?;
+
+            match entry.kind() {
+                Some(CorpusEntryKind::InputFile(_)) => {
+                    if let CorpusEntry::InputFile { input, .. } = entry.parse_entry().unwrap()? {
+                        println!("input-{}.bin\t{} bytes", input.id(), input.len());
+                    }
+                }
+                Some(_) => println!("{:?}", entry.header().path().unwrap_or_default()),
+                None => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn stat(&mut self, id: InputId) -> Result<()> {
+        let input = self.find_input(id)?;
+        println!("id:     {}", input.id());
+        println!("length: {} bytes", input.len());
+
+        if let Some(report) = &self.report {
+            match crash_reason_for(report, id) {
+                Some(reason) => println!("crash:  {reason:x?}"),
+                None => println!("crash:  (none)"),
+            }
+        }
+
+        Ok(())
+    }
+
+    fn extract(&mut self, id: InputId, dest: Option<PathBuf>) -> Result<()> {
+        let input = self.find_input(id)?;
+        let dest = dest.unwrap_or_else(|| PathBuf::from(format!("input-{}.bin", input.id())));
+
+        input
+            .write_to(fs::File::create(&dest).context("Failed to create output file")?)
+            .with_context(|| format!("Failed to write input to {dest:?}"))?;
+
+        println!("wrote {}", dest.display());
+        Ok(())
+    }
+
+    fn cat(&mut self, id: InputId) -> Result<()> {
+        let input = self.find_input(id)?;
+        input
+            .write_to(io::stdout())
+            .context("Failed to write input to stdout")
+    }
+
+    fn reproducer(&mut self, reason: &str) -> Result<()> {
+        let report = self
+            .report
+            .as_ref()
+            .context("reproducer requires --report")?;
+
+        let reproducers = shortest_reproducers(report, &mut self.archive)
+            .context("Failed to collect shortest reproducers")?;
+
+        match reproducers
+            .into_iter()
+            .find(|(crash_reason, _)| format!("{crash_reason:x?}").contains(reason))
+        {
+            Some((crash_reason, input)) => {
+                println!("shortest reproducer for {crash_reason:x?}: input-{}", input.id());
+                input
+                    .write_to(io::stdout())
+                    .context("Failed to write reproducer to stdout")
+            }
+            None => {
+                println!("no crash reason matching {reason:?} found");
+                Ok(())
+            }
+        }
+    }
+
+    fn find_input(&mut self, id: InputId) -> Result<modeling::input::InputFile> {
+        for entry in self.archive.iter::<CorpusEntryKind>()? {
+            let mut entry = entry?;
+
+            if let Some(CorpusEntryKind::InputFile(_)) = entry.kind() {
+                if let CorpusEntry::InputFile { input, .. } = entry.parse_entry().unwrap()? {
+                    if input.id() == id {
+                        return Ok(input);
+                    }
+                }
+            }
+        }
+
+        Err(anyhow::anyhow!("no input with id {id} in corpus archive"))
+    }
+}
+
+fn run(mut shell: CorpusShell) -> Result<()> {
+    let mut line = String::new();
+
+    loop {
+        print!("hoedur> ");
+        io::stdout().flush().ok();
+
+        line.clear();
+        if io::stdin().read_line(&mut line).context("read command")? == 0 {
+            break;
+        }
+
+        let mut parts = line.split_whitespace();
+        let result = match parts.next() {
+            Some("ls") => shell.ls(),
+            Some("stat") => match parts.next().and_then(|id| id.parse().ok()) {
+                Some(id) => shell.stat(id),
+                None => {
+                    println!("usage: stat <id>");
+                    Ok(())
+                }
+            },
+            Some("cat") => match parts.next().and_then(|id| id.parse().ok()) {
+                Some(id) => shell.cat(id),
+                None => {
+                    println!("usage: cat <id>");
+                    Ok(())
+                }
+            },
+            Some("extract") => match parts.next().and_then(|id| id.parse().ok()) {
+                Some(id) => shell.extract(id, parts.next().map(PathBuf::from)),
+                None => {
+                    println!("usage: extract <id> [dest]");
+                    Ok(())
+                }
+            },
+            Some("reproducer") => match parts.next() {
+                Some(reason) => shell.reproducer(reason),
+                None => {
+                    println!("usage: reproducer <reason>");
+                    Ok(())
+                }
+            },
+            Some("quit") | Some("exit") => break,
+            Some(cmd) => {
+                println!("unknown command: {cmd}");
+                Ok(())
+            }
+            None => Ok(()),
+        };
+
+        if let Err(err) = result {
+            println!("error: {err:#}");
+        }
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<()> {
+    let opt = Arguments::parse();
+
+    init_log(&opt.log_config)?;
+    log::trace!("Args: {:#?}", opt);
+
+    let shell = CorpusShell::open(opt.corpus_archive, opt.report)?;
+    run(shell)
+}
@@ -80,6 +80,13 @@ fn main() -> Result<()> {
         }
     };
 
+    // NOTE: there is no catalog/seek-table API on `archive::Archive` in this
+    // tree to jump straight to `opt.input_id` (that would require
+    // archive-crate changes this series doesn't touch), so this is a linear
+    // scan. It wouldn't actually help here even if it existed: every
+    // Common/Emulator/Modeling entry below gets copied into
+    // `exploration_archive` regardless of `opt.input_id`, so the whole
+    // archive is read once either way.
     for entry in corpus_archive.iter::<CorpusEntryKind>()? {
         let mut entry = entry?;
 
@@ -9,8 +9,8 @@ use common::{
     log::{init_log, LOG_INFO},
     FxHashMap,
 };
-use fuzzer::{CorpusEntry, CorpusEntryKind};
 use hoedur::coverage::{CoverageReport, CrashReason};
+use hoedur_analyze::shortest_reproducers;
 use modeling::input::InputId;
 use serde::Serialize;
 
@@ -134,65 +134,14 @@ fn shortest_input(opt: Arguments) -> Result<()> {
     let report = CoverageReport::load_from(&opt.report)
         .with_context(|| format!("Failed to load coverage report {:?}", opt.report))?;
 
-    // collect input->crash reason mapping
-    let mut inputs = FxHashMap::default();
-    for input in report.inputs() {
-        if let Some(crash_reason) = input.crash_reason() {
-            inputs.insert(input.id(), crash_reason);
-        }
-    }
-
     let corpus_archive = opt.corpus_archive.unwrap();
 
     log::info!("Loading corpus archive {} ...", corpus_archive.display());
     let mut corpus_archive =
         Archive::from_reader(decoder(&corpus_archive).context("Failed to load corpus archive")?);
 
-    // copy config files + collect inputs
-    let mut reproducers = FxHashMap::default();
-    for entry in corpus_archive.iter::<CorpusEntryKind>()? {
-        let mut entry = entry?;
-
-        match entry.kind() {
-            Some(CorpusEntryKind::Common(_))
-            | Some(CorpusEntryKind::Emulator(_))
-            | Some(CorpusEntryKind::Modeling(_))
-            | Some(CorpusEntryKind::Fuzzer(_)) => {
-                continue;
-            }
-            Some(CorpusEntryKind::InputFile(_)) => {
-                if let CorpusEntry::InputFile { input, .. } =
-                    entry.parse_entry().unwrap().with_context(|| {
-                        format!("Failed to parse input file {:?}", entry.header().path())
-                    })?
-                {
-                    // collect shortest input per crash reason
-                    if let Some(crash_reason) = inputs.get(&input.id()) {
-                        match reproducers.entry(crash_reason) {
-                            Entry::Vacant(entry) => {
-                                entry.insert(input);
-                            }
-                            Entry::Occupied(mut entry) => {
-                                let reproducer = entry.get_mut();
-
-                                if input.len() < reproducer.len() {
-                                    *reproducer = input;
-                                }
-                            }
-                        }
-                    }
-                } else {
-                    unreachable!()
-                }
-            }
-            None => {
-                log::warn!(
-                    "unknown corpus entry at {:?}",
-                    entry.header().path().unwrap_or_default()
-                );
-            }
-        }
-    }
+    let reproducers = shortest_reproducers(&report, &mut corpus_archive)
+        .context("Failed to collect shortest reproducers")?;
 
     let mut crashes = Vec::new();
     for (reason, input) in reproducers {
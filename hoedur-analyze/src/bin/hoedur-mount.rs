@@ -0,0 +1,346 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    io,
+    path::PathBuf,
+    time::{Duration, UNIX_EPOCH},
+};
+
+use anyhow::{Context, Result};
+use archive::Archive;
+use clap::Parser;
+use common::{
+    fs::decoder,
+    log::{init_log, LOG_INFO},
+};
+use fuser::{FileAttr, FileType, Filesystem, MountOption, ReplyAttr, ReplyData, ReplyEntry};
+use fuzzer::{CorpusEntry, CorpusEntryKind};
+use modeling::hardware::WriteTo;
+
+const TTL: Duration = Duration::from_secs(60);
+const ROOT_INODE: u64 = 1;
+const INPUT_DIR_INODE: u64 = 2;
+
+#[derive(Parser, Debug)]
+#[command(name = "hoedur-mount")]
+struct Arguments {
+    #[arg(long, default_value = LOG_INFO)]
+    log_config: PathBuf,
+
+    /// Corpus archive file to mount read-only
+    corpus_archive: PathBuf,
+
+    /// Directory to mount the archive contents at
+    mountpoint: PathBuf,
+}
+
+struct MountedFile {
+    name: String,
+    parent: u64,
+    size: u64,
+    /// this entry's position in `Archive::iter`'s order, used to find it
+    /// again on a fresh scan (see `CorpusFs::load`)
+    entry_index: usize,
+    /// decoded lazily, on first `read()` of this inode; `None` until then
+    data: Option<Vec<u8>>,
+}
+
+/// Read-only FUSE view of a corpus archive. `archive::Archive` has no
+/// seekable/catalog reader to jump straight to one entry, so `new` still
+/// has to scan the whole archive once to build the directory listing -- but
+/// it only records each entry's size and position (an `InputFile` is parsed
+/// to learn its serialized size, every other entry's bytes are read and
+/// counted; neither is kept). A file's actual content is only
+/// decoded -- by re-scanning up to its recorded position -- the first time
+/// FUSE calls `read()` on it, and cached from then on, instead of every
+/// entry being held in memory for the lifetime of the mount.
+struct CorpusFs {
+    corpus_archive: PathBuf,
+    files: HashMap<u64, MountedFile>,
+    children: HashMap<u64, Vec<u64>>,
+}
+
+impl CorpusFs {
+    fn new(corpus_archive: PathBuf) -> Result<Self> {
+        let mut archive = Archive::from_reader(
+            decoder(&corpus_archive).context("Failed to load corpus archive")?,
+        );
+
+        let mut files = HashMap::new();
+        let mut children: HashMap<u64, Vec<u64>> = HashMap::new();
+        children.insert(ROOT_INODE, vec![INPUT_DIR_INODE]);
+        children.insert(INPUT_DIR_INODE, vec![]);
+
+        let mut next_inode = INPUT_DIR_INODE + 1;
+
+        for (entry_index, entry) in archive.iter::<CorpusEntryKind>()?.enumerate() {
+            let mut entry = entry?;
+
+            let (name, parent, size) = match entry.kind() {
+                Some(CorpusEntryKind::InputFile(_)) => {
+                    if let CorpusEntry::InputFile { input, .. } = entry.parse_entry().unwrap()? {
+                        (
+                            format!("input-{}.bin", input.id()),
+                            INPUT_DIR_INODE,
+                            input.write_size()? as u64,
+                        )
+                    } else {
+                        unreachable!()
+                    }
+                }
+                Some(CorpusEntryKind::Common(_))
+                | Some(CorpusEntryKind::Emulator(_))
+                | Some(CorpusEntryKind::Modeling(_))
+                | Some(CorpusEntryKind::Fuzzer(_)) => {
+                    let path = entry.header().path().unwrap_or_default();
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_else(|| format!("entry-{}", next_inode));
+
+                    // read (but don't keep) the bytes just to learn the
+                    // size; actual content is re-read lazily in `load`
+                    let size = io::copy(&mut entry.raw_entry(), &mut io::sink())
+                        .context("Failed to size corpus entry")?;
+
+                    (name, ROOT_INODE, size)
+                }
+                None => continue,
+            };
+
+            let inode = next_inode;
+            next_inode += 1;
+
+            files.insert(
+                inode,
+                MountedFile {
+                    name,
+                    parent,
+                    size,
+                    entry_index,
+                    data: None,
+                },
+            );
+            children.entry(parent).or_default().push(inode);
+        }
+
+        Ok(CorpusFs {
+            corpus_archive,
+            files,
+            children,
+        })
+    }
+
+    /// Decode `inode`'s content and cache it, if not already cached. Still
+    /// has to re-scan the archive from the start and discard every earlier
+    /// entry's bytes (no seek/catalog API, see the struct doc comment), but
+    /// only runs for inodes FUSE actually reads, and only once each.
+    fn load(&mut self, inode: u64) -> Result<()> {
+        let Some(file) = self.files.get(&inode) else {
+            return Ok(());
+        };
+        if file.data.is_some() {
+            return Ok(());
+        }
+        let target_index = file.entry_index;
+
+        let mut archive = Archive::from_reader(
+            decoder(&self.corpus_archive).context("Failed to reopen corpus archive")?,
+        );
+
+        for (entry_index, entry) in archive.iter::<CorpusEntryKind>()?.enumerate() {
+            let mut entry = entry?;
+
+            if entry_index != target_index {
+                io::copy(&mut entry.raw_entry(), &mut io::sink())
+                    .context("Failed to skip corpus entry")?;
+                continue;
+            }
+
+            let data = match entry.kind() {
+                Some(CorpusEntryKind::InputFile(_)) => {
+                    if let CorpusEntry::InputFile { input, .. } = entry.parse_entry().unwrap()? {
+                        let mut data = Vec::with_capacity(input.write_size()?);
+                        input
+                            .write_to(&mut data)
+                            .context("Failed to serialize mounted input")?;
+                        data
+                    } else {
+                        unreachable!()
+                    }
+                }
+                _ => {
+                    let mut data = Vec::new();
+                    io::copy(&mut entry.raw_entry(), &mut data)
+                        .context("Failed to read corpus entry")?;
+                    data
+                }
+            };
+
+            self.files.get_mut(&inode).expect("checked above").data = Some(data);
+            break;
+        }
+
+        Ok(())
+    }
+
+    fn attr(&self, inode: u64) -> Option<FileAttr> {
+        if inode == ROOT_INODE || inode == INPUT_DIR_INODE {
+            return Some(dir_attr(inode));
+        }
+
+        self.files.get(&inode).map(|file| file_attr(inode, file.size))
+    }
+}
+
+fn dir_attr(inode: u64) -> FileAttr {
+    FileAttr {
+        ino: inode,
+        size: 0,
+        blocks: 0,
+        atime: UNIX_EPOCH,
+        mtime: UNIX_EPOCH,
+        ctime: UNIX_EPOCH,
+        crtime: UNIX_EPOCH,
+        kind: FileType::Directory,
+        perm: 0o555,
+        nlink: 2,
+        uid: 0,
+        gid: 0,
+        rdev: 0,
+        blksize: 512,
+        flags: 0,
+    }
+}
+
+fn file_attr(inode: u64, size: u64) -> FileAttr {
+    FileAttr {
+        ino: inode,
+        size,
+        blocks: size.div_ceil(512),
+        atime: UNIX_EPOCH,
+        mtime: UNIX_EPOCH,
+        ctime: UNIX_EPOCH,
+        crtime: UNIX_EPOCH,
+        kind: FileType::RegularFile,
+        perm: 0o444,
+        nlink: 1,
+        uid: 0,
+        gid: 0,
+        rdev: 0,
+        blksize: 512,
+        flags: 0,
+    }
+}
+
+impl Filesystem for CorpusFs {
+    fn lookup(&mut self, _req: &fuser::Request<'_>, parent: u64, name: &OsStr, reply: ReplyEntry) {
+        let name = name.to_string_lossy();
+
+        let found = self
+            .children
+            .get(&parent)
+            .into_iter()
+            .flatten()
+            .find(|inode| {
+                if **inode == INPUT_DIR_INODE {
+                    name == "input"
+                } else {
+                    self.files.get(inode).map(|f| f.name == name).unwrap_or(false)
+                }
+            })
+            .copied();
+
+        match found.and_then(|inode| self.attr(inode).map(|attr| (inode, attr))) {
+            Some((_, attr)) => reply.entry(&TTL, &attr, 0),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn getattr(&mut self, _req: &fuser::Request<'_>, inode: u64, _fh: Option<u64>, reply: ReplyAttr) {
+        match self.attr(inode) {
+            Some(attr) => reply.attr(&TTL, &attr),
+            None => reply.error(libc::ENOENT),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &fuser::Request<'_>,
+        inode: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        if !self.files.contains_key(&inode) {
+            reply.error(libc::ENOENT);
+            return;
+        }
+
+        if let Err(err) = self.load(inode) {
+            log::warn!("Failed to decode corpus entry for inode {inode}: {err:#}");
+            reply.error(libc::EIO);
+            return;
+        }
+
+        let data = self.files.get(&inode).and_then(|file| file.data.as_ref());
+        let Some(data) = data else {
+            reply.error(libc::EIO);
+            return;
+        };
+
+        let offset = offset as usize;
+        let end = (offset + size as usize).min(data.len());
+        reply.data(data.get(offset..end).unwrap_or(&[]));
+    }
+
+    fn readdir(
+        &mut self,
+        _req: &fuser::Request<'_>,
+        inode: u64,
+        _fh: u64,
+        offset: i64,
+        mut reply: fuser::ReplyDirectory,
+    ) {
+        let mut entries = vec![(inode, FileType::Directory, ".".to_string())];
+        if let Some(children) = self.children.get(&inode) {
+            for &child in children {
+                let (kind, name) = if child == INPUT_DIR_INODE {
+                    (FileType::Directory, "input".to_string())
+                } else if let Some(file) = self.files.get(&child) {
+                    (FileType::RegularFile, file.name.clone())
+                } else {
+                    continue;
+                };
+                entries.push((child, kind, name));
+            }
+        }
+
+        for (i, (inode, kind, name)) in entries.into_iter().enumerate().skip(offset as usize) {
+            if reply.add(inode, (i + 1) as i64, kind, name) {
+                break;
+            }
+        }
+        reply.ok();
+    }
+}
+
+fn main() -> Result<()> {
+    let opt = Arguments::parse();
+
+    init_log(&opt.log_config)?;
+    log::trace!("Args: {:#?}", opt);
+
+    let fs = CorpusFs::new(opt.corpus_archive)?;
+
+    log::info!("Mounting corpus archive at {}", opt.mountpoint.display());
+    fuser::mount2(
+        fs,
+        &opt.mountpoint,
+        &[MountOption::RO, MountOption::FSName("hoedur-corpus".to_string())],
+    )
+    .context("Failed to mount corpus archive")
+}
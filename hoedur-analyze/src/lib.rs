@@ -0,0 +1,69 @@
+use std::io::Read;
+
+use anyhow::{Context, Result};
+use archive::Archive;
+use common::{hashbrown::hash_map::Entry, FxHashMap};
+use fuzzer::{CorpusEntry, CorpusEntryKind};
+use hoedur::coverage::{CoverageReport, CrashReason};
+use modeling::input::{InputFile, InputId};
+
+/// For each crash reason in `report`, find the shortest input in
+/// `corpus_archive` that produced it.
+///
+/// Shared between `hoedur-eval-crash --sort-by-shortest-input` and
+/// `hoedur-corpus-shell`'s `reproducer` command.
+pub fn shortest_reproducers<R: Read>(
+    report: &CoverageReport,
+    corpus_archive: &mut Archive<R>,
+) -> Result<FxHashMap<CrashReason, InputFile>> {
+    let mut inputs = FxHashMap::default();
+    for input in report.inputs() {
+        if let Some(crash_reason) = input.crash_reason() {
+            inputs.insert(input.id(), crash_reason.clone());
+        }
+    }
+
+    let mut reproducers: FxHashMap<CrashReason, InputFile> = FxHashMap::default();
+    for entry in corpus_archive.iter::<CorpusEntryKind>()? {
+        let mut entry = entry?;
+
+        if !matches!(entry.kind(), Some(CorpusEntryKind::InputFile(_))) {
+            continue;
+        }
+
+        if let CorpusEntry::InputFile { input, .. } = entry
+            .parse_entry()
+            .unwrap()
+            .with_context(|| format!("Failed to parse input file {:?}", entry.header().path()))?
+        {
+            if let Some(crash_reason) = inputs.get(&input.id()) {
+                match reproducers.entry(crash_reason.clone()) {
+                    Entry::Vacant(entry) => {
+                        entry.insert(input);
+                    }
+                    Entry::Occupied(mut entry) => {
+                        let reproducer = entry.get_mut();
+                        if input.len() < reproducer.len() {
+                            *reproducer = input;
+                        }
+                    }
+                }
+            }
+        } else {
+            unreachable!()
+        }
+    }
+
+    Ok(reproducers)
+}
+
+/// Crash reason that `report` records for the input with `id`, or `None` if
+/// that input is not in the report or did not crash. Used by
+/// `hoedur-corpus-shell`'s `stat` command.
+pub fn crash_reason_for(report: &CoverageReport, id: InputId) -> Option<CrashReason> {
+    report
+        .inputs()
+        .iter()
+        .find(|input| input.id() == id)
+        .and_then(|input| input.crash_reason().cloned())
+}
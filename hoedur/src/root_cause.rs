@@ -154,6 +154,14 @@ impl RootCauseAnalysis {
         let mut config_archive = archive::create_archive(&base_dir, "config", true, true)
             .context("Failed to create config archive")?;
 
+        // NOTE: there is no catalog/seek-table API on `archive::Archive` in
+        // this tree to jump straight to a single entry (that would require
+        // archive-crate changes this series doesn't touch), so this is a
+        // linear scan. It wouldn't help here regardless: every
+        // Common/Emulator/Modeling entry still has to be read and copied into
+        // `config_archive` below,
+        // so the whole archive is read once either way -- a seek would only
+        // pay off for a lookup that *doesn't* also need every other entry.
         for entry in corpus_archive.iter::<CorpusEntryKind>()? {
             let mut entry = entry?;
 